@@ -1,33 +1,80 @@
 use crate::level::utils::*;
 use crate::{
     display::Display,
-    display::RENDER_DISTANCE,
+    display::{RENDER_DISTANCE, VERTICAL_RENDER_DISTANCE},
     level::terrain::{Chunk, DynTerr},
 };
 
 use crate::display::mesh::mesh_gen::ChunkNeighbors;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::io;
 use std::sync::{Arc, Mutex, mpsc};
 use std::thread;
+use std::time::{Duration, Instant};
+use terrain_gen::BorderCfg;
 
 const NUM_CHUNK_THREADS: usize = 4;
 // changing this to 1 fixed "lazy" chunk loading issue, where some close chunks
 // were not loading before further ones
 
+/// wait before a chunk that failed to load is queued again, doubled with
+/// every failure in a row up to `MAX_RETRY_DELAY`.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// offsets of the 6 chunks sharing a face with a chunk.
+const NEIGHBOR_OFFSETS: [(i32, i32, i32); 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+/// finds the loaded neighbors of the chunk at `pos`.
+fn find_neighbors(chunks: &[Chunk], pos: IntVec3) -> ChunkNeighbors<'_>
+{
+    let get = |dx: i32, dy: i32, dz: i32| -> Option<&Chunk> {
+        let target = IntVec3 {
+            x: pos.x + dx,
+            y: pos.y + dy,
+            z: pos.z + dz,
+        };
+        chunks.iter().find(|c| c.chunk_loc.loc == target)
+    };
+
+    ChunkNeighbors {
+        pos_x: get(1, 0, 0),
+        neg_x: get(-1, 0, 0),
+        pos_y: get(0, 1, 0),
+        neg_y: get(0, -1, 0),
+        pos_z: get(0, 0, 1),
+        neg_z: get(0, 0, -1),
+    }
+}
+
 pub struct ChunkWorkerPool
 {
     work_tx:   mpsc::Sender<Option<ChunkLoc>>,
-    result_rx: mpsc::Receiver<Chunk>,
+    result_rx: mpsc::Receiver<(ChunkLoc, io::Result<Chunk>)>,
     handles:   Vec<thread::JoinHandle<()>>,
     pending:   HashSet<ChunkLoc>,
+    /// chunks that failed to load, with when they may be queued again and
+    /// how many times in a row they failed.
+    failed:    HashMap<ChunkLoc, (Instant, u32)>,
+    /// edge of a finite world, chunks entirely past it are never requested.
+    border:    Option<BorderCfg>,
 }
 
 impl ChunkWorkerPool
 {
-    pub fn new(terr: Arc<Mutex<DynTerr>>) -> Self
+    pub fn new(terr: Arc<Mutex<DynTerr>>, border: Option<BorderCfg>) -> Self
     {
         let (work_tx, work_rx) = mpsc::channel::<Option<ChunkLoc>>();
-        let (result_tx, result_rx) = mpsc::channel::<Chunk>();
+        let (result_tx, result_rx) =
+            mpsc::channel::<(ChunkLoc, io::Result<Chunk>)>();
         let work_rx = Arc::new(Mutex::new(work_rx));
 
         let handles = (0..NUM_CHUNK_THREADS)
@@ -43,12 +90,8 @@ impl ChunkWorkerPool
                         match job {
                             None => break, // shutdown signal
                             Some(pos) => {
-                                let chunk = terr
-                                    .lock()
-                                    .unwrap()
-                                    .get_chunk(pos)
-                                    .unwrap();
-                                result_tx.send(chunk).unwrap();
+                                let chunk = DynTerr::get_chunk(&terr, pos);
+                                result_tx.send((pos, chunk)).unwrap();
                             }
                         }
                     }
@@ -61,6 +104,8 @@ impl ChunkWorkerPool
             result_rx,
             handles,
             pending: HashSet::new(),
+            failed: HashMap::new(),
+            border,
         }
     }
 
@@ -69,6 +114,13 @@ impl ChunkWorkerPool
         // get the player position to center the loading rings
         let player_pos = ChunkLoc::from_world_loc_rl_vec(display.cam.position);
 
+        // every ring is a column of chunk layers, the player's own layer
+        // first and then outwards, so the ground nearby fills in before the
+        // sky and the depths
+        let vertical = VERTICAL_RENDER_DISTANCE as i32;
+        let mut layers: Vec<i32> = (-vertical..=vertical).collect();
+        layers.sort_by_key(|dy| dy.abs());
+
         // iterate through distances (rings) starting from 0 (player position)
         for d in 0..RENDER_DISTANCE as i32 {
             let mut queued_in_this_ring = false;
@@ -81,23 +133,27 @@ impl ChunkWorkerPool
                         continue;
                     }
 
-                    let pos = ChunkLoc {
-                        loc: IntVec3 {
-                            x: player_pos.loc.x + x,
-                            y: 0,
-                            z: player_pos.loc.z + z,
-                        },
-                    };
-
-                    if display.is_chunk_loaded(pos)
-                        || self.pending.contains(&pos)
-                    {
-                        continue;
-                    }
+                    for &dy in &layers {
+                        let pos = ChunkLoc {
+                            loc: IntVec3 {
+                                x: player_pos.loc.x + x,
+                                y: player_pos.loc.y + dy,
+                                z: player_pos.loc.z + z,
+                            },
+                        };
 
-                    self.pending.insert(pos);
-                    self.work_tx.send(Some(pos)).unwrap();
-                    queued_in_this_ring = true;
+                        if display.is_chunk_loaded(pos)
+                            || self.pending.contains(&pos)
+                            || self.is_waiting_to_retry(pos)
+                            || !self.is_inside_border(pos)
+                        {
+                            continue;
+                        }
+
+                        self.pending.insert(pos);
+                        self.work_tx.send(Some(pos)).unwrap();
+                        queued_in_this_ring = true;
+                    }
                 }
             }
 
@@ -111,6 +167,68 @@ impl ChunkWorkerPool
         }
     }
 
+    /// unloads the chunks the player moved away from, from the terrain and
+    /// the display. chunks are kept one chunk past the loading distance, so
+    /// the ones on its edge don't load and unload over and over.
+    pub fn unload_far_chunks(
+        &mut self,
+        display: &mut Display,
+        terr: &Arc<Mutex<DynTerr>>,
+    )
+    {
+        let player_pos = ChunkLoc::from_world_loc_rl_vec(display.cam.position);
+        let is_far = |pos: ChunkLoc| {
+            let dx = (pos.loc.x - player_pos.loc.x).abs();
+            let dy = (pos.loc.y - player_pos.loc.y).abs();
+            let dz = (pos.loc.z - player_pos.loc.z).abs();
+            dx.max(dz) > RENDER_DISTANCE as i32
+                || dy > VERTICAL_RENDER_DISTANCE as i32 + 1
+        };
+
+        let mut guard = terr.lock().unwrap();
+        let far: Vec<ChunkLoc> = guard
+            .chunks
+            .iter()
+            .map(|chunk| chunk.chunk_loc)
+            .filter(|&pos| is_far(pos))
+            .collect();
+        for pos in far {
+            guard.deload_chunk(pos);
+            display.unload_chunk(pos);
+        }
+        // failures far away don't matter until the player comes back
+        self.failed.retain(|&pos, _| !is_far(pos));
+    }
+
+    /// whether any column of the chunk at `pos` is inside the world border.
+    fn is_inside_border(&self, pos: ChunkLoc) -> bool
+    {
+        let Some(border) = self.border else {
+            return true;
+        };
+        let world = pos.to_world_loc();
+        border.overlaps(world.x, world.z, CHUNKSIZE as i32)
+    }
+
+    /// whether the chunk at `pos` failed to load too recently to be queued
+    /// again.
+    fn is_waiting_to_retry(&self, pos: ChunkLoc) -> bool
+    {
+        self.failed
+            .get(&pos)
+            .is_some_and(|&(retry_at, _)| Instant::now() < retry_at)
+    }
+
+    /// holds the chunk at `pos` back from loading for a while after it
+    /// failed, longer the more often it failed in a row.
+    fn retry_later(&mut self, pos: ChunkLoc)
+    {
+        let failures = self.failed.get(&pos).map_or(0, |&(_, n)| n) + 1;
+        let delay = RETRY_DELAY * 2u32.pow((failures - 1).min(6));
+        let retry_at = Instant::now() + delay.min(MAX_RETRY_DELAY);
+        self.failed.insert(pos, (retry_at, failures));
+    }
+
     pub fn apply_ready_chunks(
         &mut self,
         display: &mut Display,
@@ -120,57 +238,39 @@ impl ChunkWorkerPool
         let guard = terr.lock().unwrap();
 
         // non-blocking pull of all finished chunks
-        while let Ok(chunk) = self.result_rx.try_recv() {
-            let pos = chunk.chunk_loc;
+        while let Ok((pos, chunk)) = self.result_rx.try_recv() {
             self.pending.remove(&pos);
-
-            // helper to safely find a neighbor in the locked terrain data
-            let get_neighbor = |dx: i32, dz: i32| -> Option<&Chunk> {
-                let target = IntVec3 {
-                    x: pos.loc.x + dx,
-                    y: 0,
-                    z: pos.loc.z + dz,
-                };
-                guard.chunks.iter().find(|c| c.chunk_loc.loc == target)
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(err) => {
+                    eprintln!("could not load chunk {:?}: {err}", pos.loc);
+                    self.retry_later(pos);
+                    continue;
+                }
             };
+            self.failed.remove(&pos);
 
             // 1. load the new chunk with its neighbors
-            let neighbors = ChunkNeighbors {
-                pos_x: get_neighbor(1, 0),
-                neg_x: get_neighbor(-1, 0),
-                pos_z: get_neighbor(0, 1),
-                neg_z: get_neighbor(0, -1),
-            };
+            let neighbors = find_neighbors(&guard.chunks, pos.loc);
             display.load_chunk(&chunk, &neighbors);
 
             // 2. refresh neighbors to cull their boundary faces against the new
             //    chunk
-            let neighbor_offsets = [(1, 0), (-1, 0), (0, 1), (0, -1)];
-            for (dx, dz) in neighbor_offsets {
-                if let Some(neighbor_chunk) = get_neighbor(dx, dz) {
-                    if display.is_chunk_loaded(neighbor_chunk.chunk_loc) {
-                        let n_pos = neighbor_chunk.chunk_loc.loc;
-                        let get_n_neighbor =
-                            |ndx: i32, ndz: i32| -> Option<&Chunk> {
-                                let target = IntVec3 {
-                                    x: n_pos.x + ndx,
-                                    y: 0,
-                                    z: n_pos.z + ndz,
-                                };
-                                guard
-                                    .chunks
-                                    .iter()
-                                    .find(|c| c.chunk_loc.loc == target)
-                            };
-
-                        let n_neighbors = ChunkNeighbors {
-                            pos_x: get_n_neighbor(1, 0),
-                            neg_x: get_n_neighbor(-1, 0),
-                            pos_z: get_n_neighbor(0, 1),
-                            neg_z: get_n_neighbor(0, -1),
-                        };
-                        display.load_chunk(neighbor_chunk, &n_neighbors);
-                    }
+            for (dx, dy, dz) in NEIGHBOR_OFFSETS {
+                let target = IntVec3 {
+                    x: pos.loc.x + dx,
+                    y: pos.loc.y + dy,
+                    z: pos.loc.z + dz,
+                };
+                let Some(neighbor_chunk) =
+                    guard.chunks.iter().find(|c| c.chunk_loc.loc == target)
+                else {
+                    continue;
+                };
+
+                if display.is_chunk_loaded(neighbor_chunk.chunk_loc) {
+                    let n_neighbors = find_neighbors(&guard.chunks, target);
+                    display.load_chunk(neighbor_chunk, &n_neighbors);
                 }
             }
         }
@@ -12,14 +12,14 @@
  */
 
 /**
- * creates a raylib mesh from raw vertex, normal, and texture data.
+ * creates a raylib mesh from raw vertex, normal, texture and color data.
  *
  * # memory management
  * this function performs a deep copy of the input arrays using `malloc`.
  * the resulting mesh must be freed using raylib's `UnloadMesh` to prevent
  * memory leaks.
  */
-Mesh GenerateVoxelMesh(float* vertices, float* normals, float* texcoords, int vertexCount) {
+Mesh GenerateVoxelMesh(float* vertices, float* normals, float* texcoords, unsigned char* colors, int vertexCount) {
     Mesh mesh = { 0 };
     mesh.vertexCount = vertexCount;
     mesh.triangleCount = vertexCount / 3;
@@ -28,6 +28,7 @@ Mesh GenerateVoxelMesh(float* vertices, float* normals, float* texcoords, int ve
     // raylib's unloadmesh will eventually call free() on these
     int vertSize = vertexCount * 3 * sizeof(float);
     int texSize = vertexCount * 2 * sizeof(float);
+    int colorSize = vertexCount * 4 * sizeof(unsigned char);
 
     mesh.vertices = (float*)malloc(vertSize);
     memcpy(mesh.vertices, vertices, vertSize);
@@ -38,6 +39,9 @@ Mesh GenerateVoxelMesh(float* vertices, float* normals, float* texcoords, int ve
     mesh.texcoords = (float*)malloc(texSize);
     memcpy(mesh.texcoords, texcoords, texSize);
 
+    mesh.colors = (unsigned char*)malloc(colorSize);
+    memcpy(mesh.colors, colors, colorSize);
+
     // upload to gpu memory immediately
     // this finalizes the mesh so it is ready for the client-side rendering call
     UploadMesh(&mesh, false);
@@ -1,13 +1,16 @@
 use crate::level::terrain::Chunk;
 use crate::level::utils::*;
 use raylib::prelude::*; // mesh comes from here now
+use terrain_gen::block_ids;
 
-/// holds references to the 4 cardinal neighbor chunks.
+/// holds references to the 6 neighbor chunks sharing a face with a chunk.
 /// used to check for solid blocks across chunk boundaries.
 pub struct ChunkNeighbors<'a>
 {
     pub pos_x: Option<&'a Chunk>,
     pub neg_x: Option<&'a Chunk>,
+    pub pos_y: Option<&'a Chunk>,
+    pub neg_y: Option<&'a Chunk>,
     pub pos_z: Option<&'a Chunk>,
     pub neg_z: Option<&'a Chunk>,
 }
@@ -23,6 +26,7 @@ unsafe extern "C" {
         vertices: *mut f32,
         normals: *mut f32,
         texcoords: *mut f32,
+        colors: *mut u8,
         vertexCount: i32,
     ) -> raylib::ffi::Mesh; // fully qualified, no import needed
 }
@@ -120,16 +124,19 @@ pub fn generate_chunk_mesh(
     let mut vertices: Vec<f32> = Vec::new();
     let mut normals: Vec<f32> = Vec::new();
     let mut texcoords: Vec<f32> = Vec::new();
+    let mut colors: Vec<u8> = Vec::new();
 
     for x in 0..CHUNKSIZE {
-        for y in 0..WORLDHEIGHT {
+        for y in 0..CHUNKSIZE {
             for z in 0..CHUNKSIZE {
-                let block = &chunk.blocks[x][y][z];
+                let block = chunk.get(x, y, z);
 
-                if block.block_id == 0 {
+                if block.block_id == block_ids::AIR {
                     continue;
                 }
 
+                let color = block_ids::color(block.block_id);
+
                 let world_x = x as f32;
                 let world_y = y as f32;
                 let world_z = z as f32;
@@ -156,6 +163,11 @@ pub fn generate_chunk_mesh(
 
                         // push texcoords
                         texcoords.extend_from_slice(&QUAD_TEXCOORDS);
+
+                        // push colors, one per vertex
+                        for _ in 0..6 {
+                            colors.extend_from_slice(&color);
+                        }
                     }
                 }
             }
@@ -169,6 +181,7 @@ pub fn generate_chunk_mesh(
             vertices.as_mut_ptr(),
             normals.as_mut_ptr(),
             texcoords.as_mut_ptr(),
+            colors.as_mut_ptr(),
             vertex_count,
         );
 
@@ -189,44 +202,38 @@ fn should_render_face(
     dz: i32,
 ) -> bool
 {
+    let size = CHUNKSIZE as i32;
     let nx = x as i32 + dx;
     let ny = y as i32 + dy;
     let nz = z as i32 + dz;
 
     // in-bounds: check this chunk
-    if nx >= 0
-        && nx < CHUNKSIZE as i32
-        && ny >= 0
-        && ny < WORLDHEIGHT as i32
-        && nz >= 0
-        && nz < CHUNKSIZE as i32
-    {
-        return chunk.blocks[nx as usize][ny as usize][nz as usize].block_id
-            == 0;
-    }
-
-    // out-of-bounds: check the neighboring chunk if available
-    // note: vertical bounds (ny) are world limits, not chunk limits
-    if ny < 0 || ny >= WORLDHEIGHT as i32 {
-        return true; // always render top/bottom of world
+    if nx >= 0 && nx < size && ny >= 0 && ny < size && nz >= 0 && nz < size {
+        return chunk.get(nx as usize, ny as usize, nz as usize).block_id
+            == block_ids::AIR;
     }
 
-    let (neighbor, local_x, local_z) = if nx < 0 {
-        (neighbors.neg_x.as_ref(), CHUNKSIZE as i32 - 1, nz)
-    } else if nx >= CHUNKSIZE as i32 {
-        (neighbors.pos_x.as_ref(), 0, nz)
+    // out-of-bounds: check the neighboring chunk if available. only one axis
+    // can be out of bounds since faces step along a single axis
+    let (neighbor, local_x, local_y, local_z) = if nx < 0 {
+        (neighbors.neg_x.as_ref(), size - 1, ny, nz)
+    } else if nx >= size {
+        (neighbors.pos_x.as_ref(), 0, ny, nz)
+    } else if ny < 0 {
+        (neighbors.neg_y.as_ref(), nx, size - 1, nz)
+    } else if ny >= size {
+        (neighbors.pos_y.as_ref(), nx, 0, nz)
     } else if nz < 0 {
-        (neighbors.neg_z.as_ref(), nx, CHUNKSIZE as i32 - 1)
-    } else if nz >= CHUNKSIZE as i32 {
-        (neighbors.pos_z.as_ref(), nx, 0)
+        (neighbors.neg_z.as_ref(), nx, ny, size - 1)
     } else {
-        return true; // should be unreachable given in-bounds check above
+        (neighbors.pos_z.as_ref(), nx, ny, 0)
     };
 
     match neighbor {
         Some(n) => {
-            n.blocks[local_x as usize][ny as usize][local_z as usize].block_id
-                == 0
+            let at = |v: i32| v as usize;
+            n.get(at(local_x), at(local_y), at(local_z)).block_id
+                == block_ids::AIR
         }
         None => true, // neighbor not loaded yet, render the face to be safe
     }
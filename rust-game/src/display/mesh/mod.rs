@@ -20,9 +20,10 @@ pub struct ChunkMesh
     pub chunk_loc: ChunkLoc,
 }
 
-pub const FFI_RED: raylib::ffi::Color =
+/// neutral material tint, block colors come from the mesh vertex colors.
+pub const FFI_WHITE: raylib::ffi::Color =
     raylib::ffi::Color {
-        r: 255, g: 0, b: 0, a: 255
+        r: 255, g: 255, b: 255, a: 255
     };
 
 impl ChunkMesh
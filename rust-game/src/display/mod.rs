@@ -1,7 +1,8 @@
 use crate::level::terrain::Chunk;
-use crate::level::utils::{CHUNKSIZE, ChunkLoc, WORLDHEIGHT, WORLDHEIGHTF32};
+use crate::level::utils::{CHUNKSIZE, ChunkLoc};
 
 use raylib::prelude::*;
+use terrain_gen::{Biome, BorderCfg, TerrainGenerator};
 
 pub mod mesh;
 
@@ -11,6 +12,20 @@ use crate::display::mesh_gen::ChunkNeighbors;
 pub const RENDER_DISTANCE: usize = 8;
 pub const REND_DIST_BLOCKS: usize = RENDER_DISTANCE * CHUNKSIZE;
 
+/// chunks loaded above and below the player's chunk.
+pub const VERTICAL_RENDER_DISTANCE: usize = 2;
+
+/// height the camera starts at, above the terrain of the default preset.
+const SPAWN_HEIGHT: f32 = 64.0;
+
+/// closest the camera gets to the world border, in blocks.
+const BORDER_MARGIN: f32 = 0.5;
+
+/// thickness in blocks of the walls marking the world border.
+const BORDER_WALL: f32 = 0.1;
+
+const BORDER_COLOR: Color = Color::new(200, 60, 60, 90);
+
 /// a display struct for client side rendering
 pub struct Display {
     chunk_meshes: Vec<ChunkMesh>,
@@ -18,16 +33,21 @@ pub struct Display {
     pub rl: RaylibHandle,
     thread: RaylibThread,
     pub cam: Camera3D,
+    /// edge of a finite world, the camera can't pass it.
+    border: Option<BorderCfg>,
+    /// column the camera was last over and its biome, sampling it is too
+    /// slow to do every frame.
+    cam_biome: Option<([i32; 2], Option<Biome>)>,
 }
 
 impl Display {
-    pub fn new() -> Self {
+    pub fn new(border: Option<BorderCfg>) -> Self {
         let (mut rl, thread) = raylib::init().build();
         rl.set_window_size(1600, 900);
         let cam = Camera3D::perspective(
             Vector3 {
                 x: -10.0,
-                y: WORLDHEIGHTF32,
+                y: SPAWN_HEIGHT,
                 z: -10.0,
             },
             Vector3 {
@@ -55,6 +75,8 @@ impl Display {
             cam,
             chunk_meshes,
             shader,
+            border,
+            cam_biome: None,
         };
     }
 
@@ -76,10 +98,15 @@ impl Display {
             chunk,
             neighbors,
             &self.shader,
-            FFI_RED,
+            FFI_WHITE,
         ))
     }
 
+    /// drops the mesh of a chunk that was unloaded.
+    pub fn unload_chunk(&mut self, chunk_pos: ChunkLoc) {
+        self.chunk_meshes.retain(|m| !m.chunk_loc.compare(chunk_pos));
+    }
+
     pub fn render_chunk_meshs(
         cam: &Camera3D,
         d: &mut RaylibMode3D<RaylibDrawHandle>,
@@ -96,7 +123,7 @@ impl Display {
         let chunk_size = CHUNKSIZE as f32;
         let chunk_center = Vector3::new(
             chunk_pos.x + chunk_size / 2.0,
-            chunk_pos.y + WORLDHEIGHT as f32 / 2.0,
+            chunk_pos.y + chunk_size / 2.0,
             chunk_pos.z + chunk_size / 2.0,
         );
 
@@ -122,11 +149,60 @@ impl Display {
         return false;
     }
 
+    /// draws the world border as four translucent walls around the camera
+    /// height.
+    fn render_border(
+        cam: &Camera3D,
+        d: &mut RaylibMode3D<RaylibDrawHandle>,
+        border: BorderCfg,
+    ) {
+        let size = border.size as f32;
+        let height = 2.0 * REND_DIST_BLOCKS as f32;
+        let y = cam.position.y;
+        for side in [-size, size] {
+            let x_wall = Vector3::new(side, y, 0.0);
+            d.draw_cube(x_wall, BORDER_WALL, height, 2.0 * size, BORDER_COLOR);
+            let z_wall = Vector3::new(0.0, y, side);
+            d.draw_cube(z_wall, 2.0 * size, height, BORDER_WALL, BORDER_COLOR);
+        }
+    }
+
+    /// pushes the camera back inside the world border, keeping the direction
+    /// it looks in.
+    fn keep_inside_border(cam: &mut Camera3D, border: BorderCfg) {
+        let limit = border.size as f32 - BORDER_MARGIN;
+        let pos = cam.position;
+        let clamped = Vector3::new(
+            pos.x.clamp(-limit, limit),
+            pos.y,
+            pos.z.clamp(-limit, limit),
+        );
+        cam.target = cam.target + (clamped - pos);
+        cam.position = clamped;
+    }
+
     // --- render logic end ---
 
-    pub fn draw_loop(&mut self) {
+    pub fn draw_loop(&mut self, generator: &dyn TerrainGenerator, seed: u64) {
         self.rl
             .update_camera(&mut self.cam, CameraMode::CAMERA_FREE);
+        if let Some(border) = self.border {
+            Self::keep_inside_border(&mut self.cam, border);
+        }
+
+        let column = [
+            self.cam.position.x.floor() as i32,
+            self.cam.position.z.floor() as i32,
+        ];
+        let biome = match self.cam_biome {
+            Some((cached, biome)) if cached == column => biome,
+            _ => {
+                let biome = generator.biome_at(column[0], column[1]);
+                self.cam_biome = Some((column, biome));
+                biome
+            }
+        };
+
         let mut d = self.rl.begin_drawing(&self.thread);
         d.clear_background(Color::DARKBLUE);
 
@@ -134,9 +210,19 @@ impl Display {
             let mut d3d = d.begin_mode3D(self.cam);
 
             Self::render_chunk_meshs(&self.cam, &mut d3d, &self.chunk_meshes);
+            // after the chunks, so the terrain shows through the walls
+            if let Some(border) = self.border {
+                Self::render_border(&self.cam, &mut d3d, border);
+            }
         }
 
         d.draw_text("Hello from Joe", 20, 20, 20, Color::BLUE);
         d.draw_text(&d.get_fps().to_string(), 20, 40, 20, Color::BLACK);
+
+        // debug overlay: biome under the camera, or the generator if the
+        // world has no biomes
+        let label = biome.map_or(generator.name(), |b| b.name());
+        d.draw_text(label, 20, 60, 20, Color::BLACK);
+        d.draw_text(&format!("seed {seed}"), 20, 80, 20, Color::BLACK);
     }
 }
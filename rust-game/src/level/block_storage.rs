@@ -0,0 +1,368 @@
+use crate::level::utils::CHUNKSIZE;
+use terrain_gen::Block;
+
+/// side length in blocks of the cubic sections a chunk is split into.
+pub const SECTION_SIZE: usize = 16;
+
+/// sections along each axis of a chunk.
+const SECTIONS: usize = CHUNKSIZE / SECTION_SIZE;
+
+/// blocks in one section.
+const SECTION_BLOCKS: usize = SECTION_SIZE * SECTION_SIZE * SECTION_SIZE;
+
+/// # category
+/// **client side processing**
+///
+/// the blocks of one chunk, palette compressed.
+///
+/// the chunk is split into cubic sections of `SECTION_SIZE` blocks. a section
+/// made of a single block, like open sky or deep stone, only stores that
+/// block. every other section keeps a palette of the block ids it contains
+/// and a bit-packed palette index per block, using as few bits as the
+/// palette needs. typical terrain sections need 4 bits per block, 2 KiB
+/// instead of the 32 KiB a plain array of blocks takes.
+#[derive(Clone)]
+pub struct BlockStorage
+{
+    sections: Vec<Section>,
+}
+
+#[derive(Clone)]
+enum Section
+{
+    /// every block of the section has this id.
+    Single(usize),
+    Packed(Packed),
+}
+
+#[derive(Clone)]
+struct Packed
+{
+    /// block ids, in the order they were first placed. ids that are
+    /// overwritten later stay in the palette.
+    palette: Vec<usize>,
+    /// bits per palette index. a power of two, so indices never straddle
+    /// two words.
+    bits:    u32,
+    words:   Vec<u64>,
+}
+
+impl Packed
+{
+    /// a section whose blocks are all `palette[0]`.
+    fn new(palette: Vec<usize>) -> Self
+    {
+        let bits = bits_for(palette.len());
+        Self {
+            palette,
+            bits,
+            words: vec![0; SECTION_BLOCKS.div_ceil((64 / bits) as usize)],
+        }
+    }
+
+    /// word holding the index of block `i` and the index's shift in it.
+    fn slot(&self, i: usize) -> (usize, u32)
+    {
+        let per_word = (64 / self.bits) as usize;
+        (i / per_word, (i % per_word) as u32 * self.bits)
+    }
+
+    fn mask(&self) -> u64
+    {
+        u64::MAX >> (64 - self.bits)
+    }
+
+    fn get(&self, i: usize) -> usize
+    {
+        let (word, shift) = self.slot(i);
+        let index = (self.words[word] >> shift) & self.mask();
+        self.palette[index as usize]
+    }
+
+    fn set_index(&mut self, i: usize, index: usize)
+    {
+        let (word, shift) = self.slot(i);
+        let mask = self.mask() << shift;
+        let word = &mut self.words[word];
+        *word = (*word & !mask) | ((index as u64) << shift);
+    }
+
+    fn set(&mut self, i: usize, block_id: usize)
+    {
+        let index = match self.palette.iter().position(|&id| id == block_id) {
+            Some(index) => index,
+            None => {
+                self.palette.push(block_id);
+                if bits_for(self.palette.len()) > self.bits {
+                    self.repack();
+                }
+                self.palette.len() - 1
+            }
+        };
+        self.set_index(i, index);
+    }
+
+    /// widens the indices to fit the grown palette.
+    fn repack(&mut self)
+    {
+        let mut wider = Packed::new(self.palette.clone());
+        for i in 0..SECTION_BLOCKS {
+            let (word, shift) = self.slot(i);
+            let index = (self.words[word] >> shift) & self.mask();
+            wider.set_index(i, index as usize);
+        }
+        *self = wider;
+    }
+}
+
+/// smallest power of two number of bits that can index a palette of `len`
+/// entries, at least 1.
+fn bits_for(len: usize) -> u32
+{
+    let needed = usize::BITS - len.saturating_sub(1).leading_zeros();
+    needed.max(1).next_power_of_two()
+}
+
+/// section holding the block at chunk local `x`, `y`, `z`, and the block's
+/// index inside it.
+fn locate(x: usize, y: usize, z: usize) -> (usize, usize)
+{
+    let (sx, sy, sz) = (x / SECTION_SIZE, y / SECTION_SIZE, z / SECTION_SIZE);
+    let (lx, ly, lz) = (x % SECTION_SIZE, y % SECTION_SIZE, z % SECTION_SIZE);
+    (
+        (sx * SECTIONS + sy) * SECTIONS + sz,
+        (lx * SECTION_SIZE + ly) * SECTION_SIZE + lz,
+    )
+}
+
+impl BlockStorage
+{
+    /// a chunk made of `block` only.
+    pub fn filled(block: Block) -> Self
+    {
+        Self {
+            sections: vec![
+                Section::Single(block.block_id);
+                SECTIONS * SECTIONS * SECTIONS
+            ],
+        }
+    }
+
+    /// fills a chunk from a function of chunk local `x`, `y`, `z`, with
+    /// palettes holding exactly the blocks each section contains.
+    pub fn from_fn(mut block: impl FnMut(usize, usize, usize) -> Block) -> Self
+    {
+        let mut sections = Vec::with_capacity(SECTIONS * SECTIONS * SECTIONS);
+        let mut ids = vec![0; SECTION_BLOCKS];
+
+        for sx in 0..SECTIONS {
+            for sy in 0..SECTIONS {
+                for sz in 0..SECTIONS {
+                    let origin = [sx, sy, sz].map(|s| s * SECTION_SIZE);
+                    for (i, id) in ids.iter_mut().enumerate() {
+                        let (lx, ly, lz) = (
+                            i / (SECTION_SIZE * SECTION_SIZE),
+                            i / SECTION_SIZE % SECTION_SIZE,
+                            i % SECTION_SIZE,
+                        );
+                        *id = block(
+                            origin[0] + lx,
+                            origin[1] + ly,
+                            origin[2] + lz,
+                        )
+                        .block_id;
+                    }
+                    sections.push(section_of(&ids));
+                }
+            }
+        }
+
+        Self {
+            sections,
+        }
+    }
+
+    /// block at chunk local `x`, `y`, `z`.
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Block
+    {
+        let (section, i) = locate(x, y, z);
+        let block_id = match &self.sections[section] {
+            Section::Single(block_id) => *block_id,
+            Section::Packed(packed) => packed.get(i),
+        };
+
+        Block {
+            block_id,
+        }
+    }
+
+    /// replaces the block at chunk local `x`, `y`, `z`.
+    pub fn set(&mut self, x: usize, y: usize, z: usize, block: Block)
+    {
+        let (section, i) = locate(x, y, z);
+        let section = &mut self.sections[section];
+        match section {
+            Section::Single(block_id) if *block_id == block.block_id => {}
+            Section::Single(block_id) => {
+                let mut packed = Packed::new(vec![*block_id]);
+                packed.set(i, block.block_id);
+                *section = Section::Packed(packed);
+            }
+            Section::Packed(packed) => packed.set(i, block.block_id),
+        }
+    }
+}
+
+/// the smallest section holding `ids`, one per block.
+fn section_of(ids: &[usize]) -> Section
+{
+    if ids.iter().all(|&id| id == ids[0]) {
+        return Section::Single(ids[0]);
+    }
+
+    let mut palette = Vec::new();
+    for &id in ids {
+        if !palette.contains(&id) {
+            palette.push(id);
+        }
+    }
+
+    let mut packed = Packed::new(palette);
+    for (i, &id) in ids.iter().enumerate() {
+        let index = packed.palette.iter().position(|&p| p == id).unwrap();
+        packed.set_index(i, index);
+    }
+    Section::Packed(packed)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn block(block_id: usize) -> Block
+    {
+        Block {
+            block_id,
+        }
+    }
+
+    #[test]
+    fn bits_for_palette_sizes()
+    {
+        assert_eq!(bits_for(1), 1);
+        assert_eq!(bits_for(2), 1);
+        assert_eq!(bits_for(3), 2);
+        assert_eq!(bits_for(17), 8);
+        assert_eq!(bits_for(256), 8);
+        assert_eq!(bits_for(257), 16);
+    }
+
+    #[test]
+    fn packed_keeps_blocks_while_bits_grow()
+    {
+        let mut packed = Packed::new(vec![0]);
+        assert_eq!(packed.bits, 1);
+
+        // a new id every 10 blocks, so every width from 1 to 16 bits is used
+        for i in 0..SECTION_BLOCKS {
+            packed.set(i, i / 10);
+            assert_eq!(packed.bits, bits_for(i / 10 + 1));
+        }
+        assert_eq!(packed.bits, 16);
+        for i in 0..SECTION_BLOCKS {
+            assert_eq!(packed.get(i), i / 10);
+        }
+
+        // ids already in the palette don't grow it
+        packed.set(0, 7);
+        assert_eq!(packed.get(0), 7);
+        assert_eq!(packed.palette.len(), SECTION_BLOCKS.div_ceil(10));
+    }
+
+    #[test]
+    fn single_sections_are_packed_on_the_first_other_block()
+    {
+        let mut storage = BlockStorage::filled(block(1));
+        storage.set(3, 4, 5, block(1));
+        assert!(matches!(storage.sections[0], Section::Single(1)));
+
+        storage.set(3, 4, 5, block(2));
+        assert!(matches!(storage.sections[0], Section::Packed(_)));
+        // the other sections are left alone
+        for section in &storage.sections[1..] {
+            assert!(matches!(section, Section::Single(1)));
+        }
+        for x in 0..SECTION_SIZE {
+            for y in 0..SECTION_SIZE {
+                for z in 0..SECTION_SIZE {
+                    let expected = if (x, y, z) == (3, 4, 5) { 2 } else { 1 };
+                    assert_eq!(storage.get(x, y, z).block_id, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn section_of_uses_the_smallest_section()
+    {
+        let ids = vec![4; SECTION_BLOCKS];
+        assert!(matches!(section_of(&ids), Section::Single(4)));
+
+        let ids: Vec<usize> = (0..SECTION_BLOCKS).map(|i| 9 - i % 3).collect();
+        let Section::Packed(packed) = section_of(&ids) else {
+            panic!("three ids need a palette");
+        };
+        assert_eq!(packed.palette, [9, 8, 7]);
+        assert_eq!(packed.bits, 2);
+        for (i, &id) in ids.iter().enumerate() {
+            assert_eq!(packed.get(i), id);
+        }
+    }
+
+    #[test]
+    fn locate_covers_every_block_once()
+    {
+        let mut seen = vec![false; CHUNKSIZE * CHUNKSIZE * CHUNKSIZE];
+        for x in 0..CHUNKSIZE {
+            for y in 0..CHUNKSIZE {
+                for z in 0..CHUNKSIZE {
+                    let (section, i) = locate(x, y, z);
+                    assert!(i < SECTION_BLOCKS);
+                    let slot = &mut seen[section * SECTION_BLOCKS + i];
+                    assert!(!*slot);
+                    *slot = true;
+                }
+            }
+        }
+        assert_eq!(locate(SECTION_SIZE, 0, 1), (SECTIONS * SECTIONS, 1));
+    }
+
+    #[test]
+    fn chunks_at_negative_coordinates_round_trip()
+    {
+        // the chunk at (-1, -2, -1), addressed by world coordinates the way
+        // the game does
+        let origin = [-32, -64, -32];
+        let id = |x: i32, y: i32, z: i32| (x * 7 + y * 3 + z).rem_euclid(19);
+        let storage = BlockStorage::from_fn(|x, y, z| {
+            block(id(
+                origin[0] + x as i32,
+                origin[1] + y as i32,
+                origin[2] + z as i32,
+            ) as usize)
+        });
+
+        for x in -32..0 {
+            for y in -64..-32 {
+                for z in -32..0 {
+                    let local = [x, y, z].map(|v: i32| {
+                        v.rem_euclid(CHUNKSIZE as i32) as usize
+                    });
+                    let stored = storage.get(local[0], local[1], local[2]);
+                    assert_eq!(stored.block_id, id(x, y, z) as usize);
+                }
+            }
+        }
+    }
+}
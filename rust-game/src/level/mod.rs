@@ -1,3 +1,5 @@
+pub mod block_storage;
+pub mod region;
 pub mod terrain;
 pub mod utils;
 
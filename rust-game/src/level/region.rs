@@ -0,0 +1,690 @@
+use crate::level::terrain::Chunk;
+use crate::level::utils::*;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use terrain_gen::{Block, block_ids};
+
+/// side length in chunks of the cube of chunks stored in one region file.
+pub const REGION_SIZE: i32 = 8;
+
+/// chunks in one region file.
+const REGION_CHUNKS: u64 = (REGION_SIZE * REGION_SIZE * REGION_SIZE) as u64;
+
+/// first bytes of every region file.
+const MAGIC: &[u8; 4] = b"RGN1";
+
+/// bytes of one offset table entry, the offset, capacity and length of a
+/// chunk's data as little endian `u32`s.
+const ENTRY_LEN: u64 = 12;
+
+/// bytes of the magic and the offset table, chunk data starts after them.
+const HEADER_LEN: u64 = MAGIC.len() as u64 + REGION_CHUNKS * ENTRY_LEN;
+
+/// chunk data is given space in multiples of this many bytes, so the gaps
+/// left by moved chunks fit other chunks again.
+const SECTOR_LEN: u64 = 256;
+
+/// first byte of the data of a chunk saved with all of its blocks.
+const TAG_FULL: u8 = 0;
+
+/// first byte of the data of a chunk saved as changes to generated terrain.
+const TAG_DELTA: u8 = 1;
+
+/// blocks in one chunk.
+const CHUNK_BLOCKS: usize = CHUNKSIZE * CHUNKSIZE * CHUNKSIZE;
+
+/// # category
+/// **client side processing**
+///
+/// chunks saved to region files in a directory.
+///
+/// each file holds a cube of `REGION_SIZE` chunks on every side. it starts
+/// with an offset table holding the offset, capacity and length of every
+/// chunk's data, all 0 for chunks that were never saved, followed by the data
+/// itself. the data starts with a tag byte telling how the chunk was saved,
+/// either with all of its blocks or as a delta holding only the blocks that
+/// differ from the generated terrain, see [`SavedChunk`]. both kinds can be
+/// mixed in one file.
+///
+/// saving never writes over the data it replaces. the new data goes to the
+/// first gap between the saved chunks that fits it, or to the end of the
+/// file, and only then is the chunk's entry pointed at it. a crash while
+/// saving leaves the chunk as it was saved before.
+///
+/// the store can be shared between threads, reads and writes of its files
+/// take turns.
+pub struct RegionStore
+{
+    dir:  PathBuf,
+    /// held while the files are read or written, so writes to one file
+    /// don't interleave.
+    lock: Mutex<()>,
+}
+
+/// # category
+/// **client side processing**
+///
+/// a chunk as read back from a region file.
+pub enum SavedChunk
+{
+    Full(Chunk),
+    /// blocks to place over the generated chunk, at their chunk local
+    /// position.
+    Delta(Vec<([usize; 3], Block)>),
+}
+
+impl RegionStore
+{
+    pub fn new(dir: impl Into<PathBuf>) -> Self
+    {
+        Self {
+            dir:  dir.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// whether the chunk at `c_loc` was saved.
+    pub fn contains(&self, c_loc: ChunkLoc) -> io::Result<bool>
+    {
+        let _files = self.lock.lock().unwrap();
+        Ok(self.entry(c_loc)?.is_some())
+    }
+
+    /// reads the chunk at `c_loc`, `NotFound` if it was never saved.
+    pub fn load(&self, c_loc: ChunkLoc) -> io::Result<SavedChunk>
+    {
+        let _files = self.lock.lock().unwrap();
+        let Some(entry) = self.entry(c_loc)? else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "chunk not saved",
+            ));
+        };
+
+        let mut file = File::open(self.path(c_loc))?;
+        let mut data = vec![0; entry.len as usize];
+        file.seek(SeekFrom::Start(entry.offset))?;
+        file.read_exact(&mut data)?;
+
+        match data.split_first() {
+            Some((&TAG_FULL, blocks)) => {
+                let ids = decode_blocks(blocks)?;
+                let index = |x, y, z| (x * CHUNKSIZE + y) * CHUNKSIZE + z;
+                let chunk = Chunk::from_fn(c_loc, |x, y, z| Block {
+                    block_id: ids[index(x, y, z)],
+                });
+                Ok(SavedChunk::Full(chunk))
+            }
+            Some((&TAG_DELTA, changes)) => {
+                Ok(SavedChunk::Delta(decode_delta(changes)?))
+            }
+            _ => Err(invalid_data("unknown chunk data")),
+        }
+    }
+
+    /// writes every block of a chunk to its region file, creating the file
+    /// if needed.
+    pub fn save(&self, chunk: &Chunk) -> io::Result<()>
+    {
+        let mut data = vec![TAG_FULL];
+        data.extend(encode_blocks(chunk)?);
+        self.write(chunk.chunk_loc, &data)
+    }
+
+    /// writes only the blocks of `chunk` that differ from `generated`, the
+    /// same chunk freshly generated.
+    pub fn save_delta(&self, chunk: &Chunk, generated: &Chunk)
+    -> io::Result<()>
+    {
+        let mut data = vec![TAG_DELTA];
+        for i in 0..CHUNK_BLOCKS {
+            let (x, y, z) = block_pos(i);
+            let block_id = chunk.get(x, y, z).block_id;
+            if block_id != generated.get(x, y, z).block_id {
+                let id = u16::try_from(block_id)
+                    .map_err(|_| invalid_input("block id too large"))?;
+                data.extend_from_slice(&(i as u16).to_le_bytes());
+                data.extend_from_slice(&id.to_le_bytes());
+            }
+        }
+        self.write(chunk.chunk_loc, &data)
+    }
+
+    /// stores the data of the chunk at `c_loc`, replacing what was saved.
+    fn write(&self, c_loc: ChunkLoc, data: &[u8]) -> io::Result<()>
+    {
+        let _files = self.lock.lock().unwrap();
+        std::fs::create_dir_all(&self.dir)?;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(self.path(c_loc))?;
+
+        if file.metadata()?.len() == 0 {
+            file.write_all(MAGIC)?;
+            file.set_len(HEADER_LEN)?;
+        }
+
+        // the chunk's own slot counts as used, its data stays intact until
+        // the entry points away from it
+        let entries = read_entries(&mut file)?;
+        let capacity = (data.len() as u64).div_ceil(SECTOR_LEN) * SECTOR_LEN;
+        let offset = free_space(&entries, capacity);
+        let entry = encode_entry(Entry {
+            offset,
+            capacity,
+            len: data.len() as u64,
+        })?;
+
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(data)?;
+        // the data has to be on disk before the entry pointing at it
+        file.sync_data()?;
+        file.seek(SeekFrom::Start(entry_pos(c_loc)))?;
+        file.write_all(&entry)
+    }
+
+    /// where the chunk's data is in its region file, `None` if it was never
+    /// saved.
+    fn entry(&self, c_loc: ChunkLoc) -> io::Result<Option<Entry>>
+    {
+        let mut file = match File::open(self.path(c_loc)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        };
+        check_magic(&mut file)?;
+
+        let mut bytes = [0; ENTRY_LEN as usize];
+        file.seek(SeekFrom::Start(entry_pos(c_loc)))?;
+        file.read_exact(&mut bytes)?;
+        Ok(decode_entry(&bytes))
+    }
+
+    /// file of the region holding the chunk at `c_loc`.
+    fn path(&self, c_loc: ChunkLoc) -> PathBuf
+    {
+        let region = |v: i32| v.div_euclid(REGION_SIZE);
+        let loc = c_loc.loc;
+        let name = format!(
+            "r.{}.{}.{}.bin",
+            region(loc.x),
+            region(loc.y),
+            region(loc.z)
+        );
+        self.dir.join(name)
+    }
+}
+
+fn invalid_input(reason: &str) -> io::Error
+{
+    io::Error::new(io::ErrorKind::InvalidInput, reason)
+}
+
+fn invalid_data(reason: &str) -> io::Error
+{
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+/// where the data of a saved chunk is in its region file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry
+{
+    offset:   u64,
+    /// bytes set aside for the chunk, at least `len`.
+    capacity: u64,
+    len:      u64,
+}
+
+fn check_magic(file: &mut File) -> io::Result<()>
+{
+    let mut magic = [0; MAGIC.len()];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not a region file"));
+    }
+    Ok(())
+}
+
+/// chunk local position of the block at index `i` in `x`, `y`, `z` order.
+fn block_pos(i: usize) -> (usize, usize, usize)
+{
+    (i / (CHUNKSIZE * CHUNKSIZE), i / CHUNKSIZE % CHUNKSIZE, i % CHUNKSIZE)
+}
+
+/// position in its region file of the offset table entry of a chunk.
+fn entry_pos(c_loc: ChunkLoc) -> u64
+{
+    let local = |v: i32| v.rem_euclid(REGION_SIZE) as u64;
+    let size = REGION_SIZE as u64;
+    let loc = c_loc.loc;
+    let index = (local(loc.x) * size + local(loc.y)) * size + local(loc.z);
+    MAGIC.len() as u64 + index * ENTRY_LEN
+}
+
+/// every entry of the offset table of a region file, for the chunks that
+/// were saved.
+fn read_entries(file: &mut File) -> io::Result<Vec<Entry>>
+{
+    check_magic(file)?;
+    let mut table = vec![0; (HEADER_LEN - MAGIC.len() as u64) as usize];
+    file.read_exact(&mut table)?;
+    Ok(table
+        .chunks_exact(ENTRY_LEN as usize)
+        .filter_map(decode_entry)
+        .collect())
+}
+
+/// an offset table entry, `None` if the chunk was never saved.
+fn decode_entry(bytes: &[u8]) -> Option<Entry>
+{
+    let field = |i: usize| {
+        let bytes = bytes[i * 4..i * 4 + 4].try_into().unwrap();
+        u32::from_le_bytes(bytes) as u64
+    };
+    let entry = Entry {
+        offset:   field(0),
+        capacity: field(1),
+        len:      field(2),
+    };
+    (entry.offset != 0).then_some(entry)
+}
+
+/// an offset table entry as written to the file.
+fn encode_entry(entry: Entry) -> io::Result<[u8; ENTRY_LEN as usize]>
+{
+    let mut bytes = [0; ENTRY_LEN as usize];
+    let fields = [entry.offset, entry.capacity, entry.len];
+    for (field, value) in bytes.chunks_exact_mut(4).zip(fields) {
+        let value = u32::try_from(value)
+            .map_err(|_| invalid_input("region file too large"))?;
+        field.copy_from_slice(&value.to_le_bytes());
+    }
+    Ok(bytes)
+}
+
+/// offset of the first gap of at least `capacity` bytes after the header
+/// that no saved chunk's slot overlaps, the end of the last slot if there is
+/// none.
+fn free_space(entries: &[Entry], capacity: u64) -> u64
+{
+    let mut slots: Vec<(u64, u64)> = entries
+        .iter()
+        .map(|entry| (entry.offset, entry.offset + entry.capacity))
+        .collect();
+    slots.sort_unstable();
+
+    let mut start = HEADER_LEN;
+    for (slot_start, slot_end) in slots {
+        if slot_start >= start + capacity {
+            break;
+        }
+        start = start.max(slot_end);
+    }
+    start
+}
+
+/// run length encodes the block ids of a chunk as pairs of little endian
+/// `u16` run length and block id, in `x`, `y`, `z` order. generated chunks
+/// are mostly long runs of air and stone, so this stays small.
+fn encode_blocks(chunk: &Chunk) -> io::Result<Vec<u8>>
+{
+    let mut runs: Vec<(u16, u16)> = Vec::new();
+    for x in 0..CHUNKSIZE {
+        for y in 0..CHUNKSIZE {
+            for z in 0..CHUNKSIZE {
+                let id = u16::try_from(chunk.get(x, y, z).block_id)
+                    .map_err(|_| invalid_input("block id too large"))?;
+                match runs.last_mut() {
+                    Some((len, last)) if *last == id => *len += 1,
+                    _ => runs.push((1, id)),
+                }
+            }
+        }
+    }
+
+    let mut data = Vec::with_capacity(runs.len() * 4);
+    for (len, id) in runs {
+        data.extend_from_slice(&len.to_le_bytes());
+        data.extend_from_slice(&id.to_le_bytes());
+    }
+    Ok(data)
+}
+
+/// block ids in `x`, `y`, `z` order from data written by [`encode_blocks`].
+fn decode_blocks(data: &[u8]) -> io::Result<Vec<usize>>
+{
+    let corrupt = || invalid_data("corrupt chunk data");
+    if !data.len().is_multiple_of(4) {
+        return Err(corrupt());
+    }
+
+    let mut ids = Vec::with_capacity(CHUNK_BLOCKS);
+    for run in data.chunks_exact(4) {
+        let len = u16::from_le_bytes([run[0], run[1]]) as usize;
+        let block_id = u16::from_le_bytes([run[2], run[3]]) as usize;
+        if block_id >= block_ids::COUNT || ids.len() + len > CHUNK_BLOCKS {
+            return Err(corrupt());
+        }
+        ids.extend(std::iter::repeat_n(block_id, len));
+    }
+
+    if ids.len() != CHUNK_BLOCKS {
+        return Err(corrupt());
+    }
+    Ok(ids)
+}
+
+/// changes written by [`RegionStore::save_delta`], as little endian `u16`
+/// block index in `x`, `y`, `z` order and block id pairs.
+fn decode_delta(data: &[u8]) -> io::Result<Vec<([usize; 3], Block)>>
+{
+    let corrupt = || invalid_data("corrupt chunk delta");
+    if !data.len().is_multiple_of(4) {
+        return Err(corrupt());
+    }
+
+    data.chunks_exact(4)
+        .map(|change| {
+            let index = u16::from_le_bytes([change[0], change[1]]) as usize;
+            let block_id = u16::from_le_bytes([change[2], change[3]]) as usize;
+            if index >= CHUNK_BLOCKS || block_id >= block_ids::COUNT {
+                return Err(corrupt());
+            }
+            let (x, y, z) = block_pos(index);
+            Ok(([x, y, z], Block {
+                block_id,
+            }))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::path::Path;
+    use terrain_gen::{WorldCfg, generator};
+
+    /// an empty directory for the region files of one test, removed again
+    /// when the test ends.
+    struct TestDir(PathBuf);
+
+    impl TestDir
+    {
+        fn new(name: &str) -> Self
+        {
+            let dir = std::env::temp_dir()
+                .join(format!("regions-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            Self(dir)
+        }
+
+        fn path(&self) -> &Path
+        {
+            &self.0
+        }
+    }
+
+    impl Drop for TestDir
+    {
+        fn drop(&mut self)
+        {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn chunk_loc(x: i32, y: i32, z: i32) -> ChunkLoc
+    {
+        ChunkLoc {
+            loc: IntVec3 {
+                x,
+                y,
+                z,
+            },
+        }
+    }
+
+    /// chunk whose block ids change every `run` blocks along `z`, so a
+    /// shorter run saves to more data.
+    fn striped(c_loc: ChunkLoc, run: usize) -> Chunk
+    {
+        Chunk::from_fn(c_loc, |x, y, z| Block {
+            block_id: (x + y + z / run) % block_ids::COUNT,
+        })
+    }
+
+    /// the chunk at `c_loc` of the default world.
+    fn generated(c_loc: ChunkLoc) -> Chunk
+    {
+        let generator = generator::from_cfg(&WorldCfg::default());
+        let origin = c_loc.to_world_loc();
+        let volume = generator.gen_chunk(
+            [origin.x, origin.y, origin.z],
+            CHUNKSIZE,
+            CHUNKSIZE,
+        );
+        Chunk::from_fn(c_loc, |x, y, z| volume.get(x, y, z))
+    }
+
+    fn load_full(store: &RegionStore, c_loc: ChunkLoc) -> Chunk
+    {
+        match store.load(c_loc).unwrap() {
+            SavedChunk::Full(chunk) => chunk,
+            SavedChunk::Delta(_) => panic!("expected every block"),
+        }
+    }
+
+    fn load_delta(store: &RegionStore, c_loc: ChunkLoc)
+    -> Vec<([usize; 3], Block)>
+    {
+        match store.load(c_loc).unwrap() {
+            SavedChunk::Delta(changes) => changes,
+            SavedChunk::Full(_) => panic!("expected a delta"),
+        }
+    }
+
+    fn assert_same(a: &Chunk, b: &Chunk)
+    {
+        assert!(a.chunk_loc.compare(b.chunk_loc));
+        for i in 0..CHUNK_BLOCKS {
+            let (x, y, z) = block_pos(i);
+            assert_eq!(a.get(x, y, z).block_id, b.get(x, y, z).block_id);
+        }
+    }
+
+    fn file_len(path: &Path) -> u64
+    {
+        std::fs::metadata(path).unwrap().len()
+    }
+
+    #[test]
+    fn saved_chunks_load_back()
+    {
+        let dir = TestDir::new("round-trip");
+        let store = RegionStore::new(dir.path());
+        let c_loc = chunk_loc(1, 2, 3);
+        let chunk = striped(c_loc, 4);
+
+        assert!(!store.contains(c_loc).unwrap());
+        store.save(&chunk).unwrap();
+
+        assert!(store.contains(c_loc).unwrap());
+        assert_same(&load_full(&store, c_loc), &chunk);
+        // the rest of the region stays unsaved
+        let missing = store.load(chunk_loc(1, 2, 4)).err().unwrap();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn negative_chunks_get_their_own_regions()
+    {
+        let dir = TestDir::new("negative");
+        let store = RegionStore::new(dir.path());
+        let saved = [
+            (chunk_loc(-1, -1, -1), 8),
+            (chunk_loc(-8, 0, -9), 4),
+            (chunk_loc(7, -8, 0), 2),
+        ];
+
+        for (c_loc, run) in saved {
+            store.save(&striped(c_loc, run)).unwrap();
+        }
+        for (c_loc, run) in saved {
+            assert_same(&load_full(&store, c_loc), &striped(c_loc, run));
+        }
+
+        assert!(dir.path().join("r.-1.-1.-1.bin").exists());
+        assert!(dir.path().join("r.-1.0.-2.bin").exists());
+        assert!(dir.path().join("r.0.-1.0.bin").exists());
+        // same place in its region as (-1, -1, -1), but in another region
+        assert!(!store.contains(chunk_loc(7, 7, 7)).unwrap());
+    }
+
+    #[test]
+    fn saving_again_keeps_the_old_data_until_the_entry_moves()
+    {
+        let dir = TestDir::new("overwrite");
+        let store = RegionStore::new(dir.path());
+        let path = dir.path().join("r.0.0.0.bin");
+        let c_loc = chunk_loc(0, 0, 0);
+        let other = striped(chunk_loc(0, 0, 1), 8);
+        let large = striped(c_loc, 8);
+        let small = striped(c_loc, CHUNKSIZE);
+
+        store.save(&large).unwrap();
+        store.save(&other).unwrap();
+        let old = store.entry(c_loc).unwrap().unwrap();
+        let old_data = std::fs::read(&path).unwrap();
+        let old_slot = old.offset as usize..(old.offset + old.len) as usize;
+
+        store.save(&small).unwrap();
+        assert_same(&load_full(&store, c_loc), &small);
+        // written elsewhere, the replaced data is still intact
+        let new = store.entry(c_loc).unwrap().unwrap();
+        assert!(new.offset >= old.offset + old.capacity);
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(data[old_slot.clone()], old_data[old_slot]);
+        let size = file_len(&path);
+
+        // the space given up is used again, the file doesn't grow
+        store.save(&large).unwrap();
+        assert_same(&load_full(&store, c_loc), &large);
+        assert_eq!(store.entry(c_loc).unwrap().unwrap().offset, old.offset);
+        store.save(&small).unwrap();
+        assert_same(&load_full(&store, c_loc), &small);
+        assert_eq!(file_len(&path), size);
+
+        // more than any gap holds goes to the end of the file
+        let larger = striped(c_loc, 1);
+        store.save(&larger).unwrap();
+        assert_same(&load_full(&store, c_loc), &larger);
+        assert!(file_len(&path) > size);
+        assert_same(&load_full(&store, other.chunk_loc), &other);
+    }
+
+    #[test]
+    fn files_without_the_magic_are_rejected()
+    {
+        let dir = TestDir::new("magic");
+        let path = dir.path().join("r.0.0.0.bin");
+        let contents = b"RGNX is not a region file";
+        std::fs::create_dir_all(dir.path()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+
+        let store = RegionStore::new(dir.path());
+        let c_loc = chunk_loc(1, 1, 1);
+        let err = store.contains(c_loc).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = store.load(c_loc).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = store.save(&striped(c_loc, 8)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        assert_eq!(std::fs::read(&path).unwrap(), contents);
+    }
+
+    #[test]
+    fn unchanged_chunks_save_an_empty_delta()
+    {
+        let dir = TestDir::new("empty-delta");
+        let store = RegionStore::new(dir.path());
+        let c_loc = chunk_loc(0, 0, 0);
+        let chunk = generated(c_loc);
+
+        store.save_delta(&chunk, &chunk.clone()).unwrap();
+
+        assert!(store.contains(c_loc).unwrap());
+        assert!(load_delta(&store, c_loc).is_empty());
+    }
+
+    #[test]
+    fn deltas_restore_changes_over_regenerated_terrain()
+    {
+        let dir = TestDir::new("delta");
+        let store = RegionStore::new(dir.path());
+        let c_loc = chunk_loc(-1, 0, 2);
+        let mut chunk = generated(c_loc);
+        let placed = [
+            ([0, 0, 0], block_ids::DIAMOND_ORE),
+            ([31, 31, 31], block_ids::STONE),
+            ([5, 17, 9], block_ids::AIR),
+        ];
+        for ([x, y, z], block_id) in placed {
+            chunk.set(x, y, z, Block {
+                block_id,
+            });
+        }
+        store.save_delta(&chunk, &generated(c_loc)).unwrap();
+
+        let mut restored = generated(c_loc);
+        let changes = load_delta(&store, c_loc);
+        assert!(changes.len() <= placed.len());
+        for ([x, y, z], block) in changes {
+            restored.set(x, y, z, block);
+        }
+        assert_same(&restored, &chunk);
+
+        // full and delta saves replace each other
+        store.save(&chunk).unwrap();
+        assert_same(&load_full(&store, c_loc), &chunk);
+        store.save_delta(&chunk, &generated(c_loc)).unwrap();
+        assert!(load_delta(&store, c_loc).len() <= placed.len());
+    }
+
+    #[test]
+    fn corrupt_deltas_are_rejected()
+    {
+        let dir = TestDir::new("corrupt-delta");
+        let store = RegionStore::new(dir.path());
+        let c_loc = chunk_loc(0, 0, 0);
+        let change = |index: u16, block_id: u16| {
+            let mut data = vec![TAG_DELTA];
+            data.extend_from_slice(&index.to_le_bytes());
+            data.extend_from_slice(&block_id.to_le_bytes());
+            data
+        };
+        let count = block_ids::COUNT as u16;
+
+        let corrupt = [
+            // cut off in the middle of a change
+            change(3, 1)[..4].to_vec(),
+            change(3, count),
+            change(CHUNK_BLOCKS as u16, 1),
+            // unknown tag
+            vec![7, 0, 0, 1, 0],
+        ];
+        for data in corrupt {
+            store.write(c_loc, &data).unwrap();
+            let err = store.load(c_loc).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -1,22 +1,58 @@
+use crate::level::block_storage::BlockStorage;
+use crate::level::region::{RegionStore, SavedChunk};
 use crate::level::utils::*;
 use noiselib::*;
-use terrain_gen::{Block, WorldCfg};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use terrain_gen::{Block, CfgError, TerrainGenerator, WorldCfg};
 
-const WORLDCFG: WorldCfg = WorldCfg {
-    world_size_b: WORLDSIZE_BLOCKS,
-    world_height: WORLDHEIGHT,
-    seed:         10,
-};
+/// world preset used when none is given on the command line.
+pub const DEFAULT_PRESET: &str = "resources/worlds/default.toml";
+
+/// directory the world is saved in.
+pub const WORLD_DIR: &str = "saves/world";
+
+/// region files of the saved world's chunks.
+pub fn region_dir() -> PathBuf
+{
+    Path::new(WORLD_DIR).join("regions")
+}
+
+/// settings of the saved world, with the seed it was created with.
+pub fn world_cfg_path() -> PathBuf
+{
+    Path::new(WORLD_DIR).join("world.toml")
+}
+
+/// stores the settings of a new world, so it generates the same terrain the
+/// next time it is opened.
+pub fn save_world_cfg(cfg: &WorldCfg) -> Result<(), CfgError>
+{
+    std::fs::create_dir_all(WORLD_DIR).map_err(CfgError::Io)?;
+    cfg.save(world_cfg_path())
+}
+
+/// save mode of the saved world, next to its settings.
+pub fn save_mode_path() -> PathBuf
+{
+    Path::new(WORLD_DIR).join("save_mode")
+}
 
 /// # category
 /// **client side processing**
 ///
-/// a 3d container for blocks.
+/// a cube of blocks, `CHUNKSIZE` on every side.
+///
+/// chunks are stacked vertically as well as horizontally, so the world has
+/// no fixed height. blocks are palette compressed, see [`BlockStorage`], and
+/// read and written through [`Chunk::get`] and [`Chunk::set`].
 #[derive(Clone)]
 pub struct Chunk
 {
     pub chunk_loc: ChunkLoc,
-    pub blocks:    Box<[[[Block; CHUNKSIZE]; WORLDHEIGHT]; CHUNKSIZE]>,
+    blocks:        BlockStorage,
+    /// whether a block was set since the chunk was generated or loaded.
+    modified:      bool,
 }
 
 impl Chunk
@@ -28,30 +64,112 @@ impl Chunk
             chunk_loc: ChunkLoc {
                 loc: IntVec3::zero()
             },
-            blocks:    Box::new(
-                [[[Block {
-                    block_id: 1
-                }; CHUNKSIZE]; WORLDHEIGHT]; CHUNKSIZE],
-            ),
+            blocks:    BlockStorage::filled(Block {
+                block_id: 1,
+            }),
+            modified:  false,
         }
     }
 
-    /// generates terrain using terrain-gen
-    pub fn gen_terr(&mut self)
+    /// creates a chunk at `chunk_loc` from a function of chunk local `x`,
+    /// `y`, `z`.
+    pub fn from_fn(
+        chunk_loc: ChunkLoc,
+        block: impl FnMut(usize, usize, usize) -> Block,
+    ) -> Self
     {
-        for x in 0..CHUNKSIZE {
-            for y in 0..WORLDHEIGHT {
-                for z in 0..CHUNKSIZE {
-                    let offset_int = self.chunk_loc.to_world_loc();
-                    let b_x = offset_int.x + (x as i32);
-                    let b_y = offset_int.y + (y as i32);
-                    let b_z = offset_int.z + (z as i32);
+        Self {
+            chunk_loc,
+            blocks: BlockStorage::from_fn(block),
+            modified: false,
+        }
+    }
 
-                    self.blocks[x][y][z] =
-                        terrain_gen::block_gen(b_x, b_y, b_z, WORLDCFG);
-                }
+    /// block at chunk local `x`, `y`, `z`.
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Block
+    {
+        self.blocks.get(x, y, z)
+    }
+
+    /// replaces the block at chunk local `x`, `y`, `z`.
+    pub fn set(&mut self, x: usize, y: usize, z: usize, block: Block)
+    {
+        self.blocks.set(x, y, z, block);
+        self.modified = true;
+    }
+
+    /// generates terrain using the world's terrain-gen generator
+    pub fn gen_terr(&mut self, generator: &dyn TerrainGenerator)
+    {
+        let offset_int = self.chunk_loc.to_world_loc();
+        let volume = generator.gen_chunk(
+            [offset_int.x, offset_int.y, offset_int.z],
+            CHUNKSIZE,
+            CHUNKSIZE,
+        );
+
+        self.blocks = BlockStorage::from_fn(|x, y, z| volume.get(x, y, z));
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// how chunks are written to disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveMode
+{
+    /// every block of every chunk, saved when it is first generated.
+    Full,
+    /// only the blocks the player changed, saved when a chunk was modified.
+    /// the rest is generated again on load, which relies on the world's
+    /// generator version producing the same terrain. untouched chunks take
+    /// no space at all.
+    Delta,
+}
+
+impl SaveMode
+{
+    fn name(self) -> &'static str
+    {
+        match self {
+            SaveMode::Full => "full",
+            SaveMode::Delta => "delta",
+        }
+    }
+
+    /// reads the save mode a world was created with. worlds saved before
+    /// there were save modes have every chunk saved in full, so a missing
+    /// file reads as [`SaveMode::Full`].
+    pub fn load_saved(path: impl AsRef<Path>) -> std::io::Result<Self>
+    {
+        let name = match std::fs::read_to_string(path) {
+            Ok(name) => name,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(SaveMode::Full);
             }
+            Err(err) => return Err(err),
+        };
+        [SaveMode::Full, SaveMode::Delta]
+            .into_iter()
+            .find(|mode| mode.name() == name.trim())
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("unknown save mode `{}`", name.trim()),
+                )
+            })
+    }
+
+    /// stores the save mode of a new world, so it keeps saving chunks the
+    /// same way.
+    pub fn save(self, path: impl AsRef<Path>) -> std::io::Result<()>
+    {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
         }
+        std::fs::write(path, self.name())
     }
 }
 
@@ -59,83 +177,166 @@ impl Chunk
 /// **client side processing**
 ///
 /// manager for dynamic world loading and unloading.
+///
+/// chunks are kept in ram while loaded and saved to region files according
+/// to the [`SaveMode`]. chunks saved in either mode load the same, so a
+/// world can switch between them.
 pub struct DynTerr
 {
-    pub chunks: Vec<Chunk>,
+    pub chunks:    Vec<Chunk>,
+    pub generator: Arc<dyn TerrainGenerator>,
+    regions:       Arc<RegionStore>,
+    save_mode:     SaveMode,
 }
 
 impl DynTerr
 {
-    /// initializes an empty terrain manager.
-    pub fn new() -> Self
+    /// initializes an empty terrain manager for a world whose chunks are
+    /// saved in `region_dir`.
+    pub fn new(
+        generator: Arc<dyn TerrainGenerator>,
+        region_dir: impl Into<PathBuf>,
+        save_mode: SaveMode,
+    ) -> Self
     {
         Self {
-            chunks: Vec::new()
+            chunks: Vec::new(),
+            generator,
+            regions: Arc::new(RegionStore::new(region_dir)),
+            save_mode,
         }
     }
 
-    /// retrieves a chunk or generates it if missing.
+    /// retrieves a chunk from ram or disk, or generates it if it doesn't
+    /// exist yet.
+    ///
+    /// `terr` is only locked to look the chunk up and to add it. reading,
+    /// generating and saving the chunk happen unlocked, so chunk workers
+    /// don't wait on each other's disk access.
     pub fn get_chunk(
-        &mut self,
+        terr: &Mutex<DynTerr>,
         c_loc: ChunkLoc,
     ) -> Result<Chunk, std::io::Error>
     {
-        if self.should_gen_chunk(c_loc) {
-            let chunk = Self::gen_chunk(c_loc);
-            self.chunks.push(chunk.clone());
-            return Ok(chunk);
+        let (generator, regions, save_mode) = {
+            let terr = terr.lock().unwrap();
+            if let Some(chunk) =
+                terr.chunks.iter().find(|c| c.chunk_loc.compare(c_loc))
+            {
+                return Ok(chunk.clone());
+            }
+            let generator = Arc::clone(&terr.generator);
+            (generator, Arc::clone(&terr.regions), terr.save_mode)
+        };
+
+        let chunk = if regions.contains(c_loc)? {
+            Self::load_chunk(c_loc, &regions, generator.as_ref())?
         } else {
-            for chunk in &self.chunks {
-                if chunk.chunk_loc.compare(c_loc) {
-                    return Ok(chunk.clone());
-                }
+            let chunk = Self::gen_chunk(c_loc, generator.as_ref());
+            // the chunk can be generated again, not saving it loses nothing
+            if save_mode == SaveMode::Full
+                && let Err(err) = regions.save(&chunk)
+            {
+                eprintln!("could not save chunk {:?}: {err}", c_loc.loc);
             }
-        }
+            chunk
+        };
 
-        Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "chunk not found",
-        ))
+        let mut terr = terr.lock().unwrap();
+        // another worker may have added it while this one was unlocked
+        if let Some(chunk) =
+            terr.chunks.iter().find(|c| c.chunk_loc.compare(c_loc))
+        {
+            return Ok(chunk.clone());
+        }
+        terr.chunks.push(chunk.clone());
+        Ok(chunk)
     }
 
-    /// removes a chunk from memory.
+    /// removes a chunk from memory, writing it to disk first if it was
+    /// modified.
     pub fn deload_chunk(&mut self, c_loc: ChunkLoc) -> bool
     {
-        if self.is_chunk_loaded(c_loc) {
-            for (idx, chunk) in self.chunks.iter().enumerate() {
-                if chunk.chunk_loc.compare(c_loc) {
-                    self.chunks.remove(idx);
-                    return true;
+        let Some(idx) =
+            self.chunks.iter().position(|c| c.chunk_loc.compare(c_loc))
+        else {
+            return false;
+        };
+
+        let chunk = self.chunks.remove(idx);
+        if chunk.modified
+            && let Err(err) = self.save_chunk(&chunk)
+        {
+            eprintln!("could not save chunk {:?}: {err}", c_loc.loc);
+        }
+        true
+    }
+
+    /// writes every modified chunk still in memory to disk, so no changes
+    /// are lost when the game closes.
+    pub fn save_modified(&mut self)
+    {
+        for idx in 0..self.chunks.len() {
+            let chunk = &self.chunks[idx];
+            if !chunk.modified {
+                continue;
+            }
+            match self.save_chunk(chunk) {
+                Ok(()) => self.chunks[idx].modified = false,
+                Err(err) => eprintln!(
+                    "could not save chunk {:?}: {err}",
+                    chunk.chunk_loc.loc
+                ),
+            }
+        }
+    }
+
+    /// reads a saved chunk, generating the blocks a delta leaves out.
+    fn load_chunk(
+        c_loc: ChunkLoc,
+        regions: &RegionStore,
+        generator: &dyn TerrainGenerator,
+    ) -> Result<Chunk, std::io::Error>
+    {
+        match regions.load(c_loc)? {
+            SavedChunk::Full(chunk) => Ok(chunk),
+            SavedChunk::Delta(changes) => {
+                let mut chunk = Self::gen_chunk(c_loc, generator);
+                for ([x, y, z], block) in changes {
+                    chunk.set(x, y, z, block);
                 }
+                // matches what is on disk
+                chunk.modified = false;
+                Ok(chunk)
+            }
+        }
+    }
+
+    /// writes a chunk to disk in the world's save mode.
+    fn save_chunk(&self, chunk: &Chunk) -> Result<(), std::io::Error>
+    {
+        match self.save_mode {
+            SaveMode::Full => self.regions.save(chunk),
+            SaveMode::Delta => {
+                let generated =
+                    Self::gen_chunk(chunk.chunk_loc, self.generator.as_ref());
+                self.regions.save_delta(chunk, &generated)
             }
         }
-        false
     }
 
     /// creates and proceduralizes a new chunk.
-    fn gen_chunk(c_loc: ChunkLoc) -> Chunk
+    fn gen_chunk(c_loc: ChunkLoc, generator: &dyn TerrainGenerator) -> Chunk
     {
         let mut chunk = Chunk::new();
         chunk.chunk_loc = c_loc;
-        chunk.gen_terr();
+        chunk.gen_terr(generator);
         chunk
     }
 
-    /// checks if chunk exists in persistent storage.
-    fn does_chunk_exist(&self, _c_loc: ChunkLoc) -> bool
-    {
-        false
-    }
-
     /// checks if chunk is currently in ram.
     pub fn is_chunk_loaded(&self, c_loc: ChunkLoc) -> bool
     {
         self.chunks.iter().any(|c| c.chunk_loc.compare(c_loc))
     }
-
-    /// determines if a chunk needs to be generated.
-    fn should_gen_chunk(&self, c_loc: ChunkLoc) -> bool
-    {
-        !self.is_chunk_loaded(c_loc) && !self.does_chunk_exist(c_loc)
-    }
 }
@@ -1,11 +1,7 @@
 use raylib::prelude::*;
 
+/// side length in blocks of the cubic chunks, on every axis.
 pub const CHUNKSIZE: usize = 32;
-pub const WORLDHEIGHT: usize = 64;
-pub const WORLDHEIGHTF32: f32 = WORLDHEIGHT as f32;
-pub const WORLDSIZE_CHUNK_REL: usize = 8;
-pub const WORLDSIZE_CHUNK: usize = WORLDSIZE_CHUNK_REL * 2;
-pub const WORLDSIZE_BLOCKS: usize = WORLDSIZE_CHUNK * CHUNKSIZE;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct IntVec3
@@ -65,12 +61,9 @@ impl ChunkLoc
         };
     }
 
+    /// whether both locations are the same chunk.
     pub fn compare(&self, other: ChunkLoc) -> bool
     {
-        if self.loc.x == other.loc.x && self.loc.z == other.loc.z {
-            return true;
-        } else {
-            return false;
-        }
+        self.loc == other.loc
     }
 }
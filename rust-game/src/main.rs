@@ -3,32 +3,108 @@ mod display;
 mod level;
 
 use crate::chunk_loader::ChunkWorkerPool;
-use crate::level::terrain::DynTerr;
+use crate::level::terrain::{
+    DEFAULT_PRESET, DynTerr, SaveMode, region_dir, save_mode_path,
+    save_world_cfg, world_cfg_path,
+};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use terrain_gen::WorldCfg;
 
 
 
 fn main()
 {
+    // `--delta-saves` makes a new world only write the blocks the player
+    // changed
+    let (flags, args): (Vec<String>, Vec<String>) =
+        std::env::args().skip(1).partition(|arg| arg.starts_with("--"));
+    let mut delta_saves = false;
+    for flag in flags {
+        match flag.as_str() {
+            "--delta-saves" => delta_saves = true,
+            _ => eprintln!("{flag}: unknown option, ignored"),
+        }
+    }
+
+    // a saved world keeps the settings it was created with, the preset only
+    // applies to new worlds. the first other argument overrides the default
+    // one
+    let preset = args.into_iter().next();
+    let saved = world_cfg_path();
+    let is_new = !saved.exists();
+    let path = if is_new {
+        PathBuf::from(preset.as_deref().unwrap_or(DEFAULT_PRESET))
+    } else {
+        if let Some(preset) = &preset {
+            eprintln!("{preset}: ignored, opening the saved world");
+        }
+        saved
+    };
+
+    let loaded = if is_new {
+        WorldCfg::load(&path)
+    } else {
+        WorldCfg::load_saved(&path)
+    };
+    let cfg = match loaded {
+        Ok(cfg) => cfg,
+        Err(err) => {
+            eprintln!("{}: {err}", path.display());
+            std::process::exit(1);
+        }
+    };
+    if is_new && let Err(err) = save_world_cfg(&cfg) {
+        eprintln!("could not save the world settings: {err}");
+    }
+
+    // like the settings, the save mode stays what the world was created with
+    let save_mode = if is_new {
+        let save_mode = if delta_saves {
+            SaveMode::Delta
+        } else {
+            SaveMode::Full
+        };
+        if let Err(err) = save_mode.save(save_mode_path()) {
+            eprintln!("could not save the save mode: {err}");
+        }
+        save_mode
+    } else {
+        if delta_saves {
+            eprintln!("--delta-saves: ignored, opening the saved world");
+        }
+        match SaveMode::load_saved(save_mode_path()) {
+            Ok(save_mode) => save_mode,
+            Err(err) => {
+                eprintln!("{}: {err}", save_mode_path().display());
+                std::process::exit(1);
+            }
+        }
+    };
+
     // initialize display and frame rate
-    let mut display = display::Display::new();
+    let mut display = display::Display::new(cfg.border);
     display.rl.set_target_fps(1000);
 
     // setup terrain data and thread pool
-    let terr = Arc::new(Mutex::new(DynTerr::new()));
-    let mut pool = ChunkWorkerPool::new(Arc::clone(&terr));
+    let generator = terrain_gen::generator::from_cfg(&cfg);
+    let terr = DynTerr::new(Arc::clone(&generator), region_dir(), save_mode);
+    let terr = Arc::new(Mutex::new(terr));
+    let mut pool = ChunkWorkerPool::new(Arc::clone(&terr), cfg.border);
 
     if !display.rl.window_should_close() {
-        display.draw_loop();
+        display.draw_loop(generator.as_ref(), cfg.seed);
     }
 
     while !display.rl.window_should_close() {
         pool.queue_missing_chunks(&display);
         pool.apply_ready_chunks(&mut display, &terr);
+        pool.unload_far_chunks(&mut display, &terr);
 
-        display.draw_loop();
+        display.draw_loop(generator.as_ref(), cfg.seed);
     }
 
     // cleanup threads before exiting
     pool.shutdown();
+    terr.lock().unwrap().save_modified();
 }
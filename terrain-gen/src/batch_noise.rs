@@ -0,0 +1,337 @@
+use noiselib::perlin::{NOISE_PERM, perlin_noise_2d};
+use noiselib::prelude::UniformRandomGen;
+
+/// multipliers spreading lattice coordinates over the hash.
+const HASH_X: u32 = 0x8DA6_B343;
+const HASH_Y: u32 = 0xD816_3841;
+const HASH_Z: u32 = 0x5A62_4C8F;
+
+/// final multiplier of the lattice hash.
+const HASH_MIX: u32 = 0x2C1B_3C6D;
+
+/// positions evaluated per simd step.
+const LANES: usize = 8;
+
+/// hash of the 3d lattice point `x`, `y`, `z`, only its four lowest bits are
+/// used.
+fn hash_3d(x: i32, y: i32, z: i32, seed: u32) -> u32
+{
+    let h = seed
+        ^ (x as u32).wrapping_mul(HASH_X)
+        ^ (y as u32).wrapping_mul(HASH_Y)
+        ^ (z as u32).wrapping_mul(HASH_Z);
+    let h = h.wrapping_mul(HASH_MIX);
+    h ^ (h >> 16)
+}
+
+/// lattice coordinate of an already floored position. out of range values
+/// map to `i32::MIN`.
+fn lattice(v: f32) -> i32
+{
+    if (-2_147_483_648.0..2_147_483_648.0).contains(&v) {
+        v as i32
+    } else {
+        i32::MIN
+    }
+}
+
+/// `v` with its sign flipped when `bit` of `h` is set.
+fn flip(v: f32, h: u32, bit: u32) -> f32
+{
+    f32::from_bits(v.to_bits() ^ (((h >> bit) & 1) << 31))
+}
+
+/// one of the twelve gradients towards the edges of a cube, picked by `h`,
+/// dotted with the offset `x`, `y`, `z` from its lattice point.
+fn grad_3d(h: u32, x: f32, y: f32, z: f32) -> f32
+{
+    let h = h & 15;
+    let u = if h < 8 { x } else { y };
+    let v = match h {
+        0..4 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+    flip(u, h, 0) + flip(v, h, 1)
+}
+
+fn fade(t: f32) -> f32
+{
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32
+{
+    a + t * (b - a)
+}
+
+/// # category
+/// **client side processing**
+///
+/// 3d gradient noise in about `-1.0..=1.0` at a single position, used from
+/// generator version 2 on. 3d fields are sampled block by block, so there is
+/// no batched version.
+pub fn gradient_3d_at(x: f32, y: f32, z: f32, seed: u32) -> f32
+{
+    let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+    let (fx, fy, fz) = (x - x0, y - y0, z - z0);
+    let (ix, iy, iz) = (lattice(x0), lattice(y0), lattice(z0));
+
+    // corner `i` is one step further along x, y and z for bits 0, 1 and 2
+    let corner = |i: i32| {
+        let (dx, dy, dz) = (i & 1, (i >> 1) & 1, i >> 2);
+        let h = hash_3d(
+            ix.wrapping_add(dx),
+            iy.wrapping_add(dy),
+            iz.wrapping_add(dz),
+            seed,
+        );
+        grad_3d(h, fx - dx as f32, fy - dy as f32, fz - dz as f32)
+    };
+
+    let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+    let near = lerp(
+        lerp(corner(0), corner(1), u),
+        lerp(corner(2), corner(3), u),
+        v,
+    );
+    let far = lerp(
+        lerp(corner(4), corner(5), u),
+        lerp(corner(6), corner(7), u),
+        v,
+    );
+    lerp(near, far, w)
+}
+
+/// # category
+/// **client side processing**
+///
+/// scalar fallback of [`perlin_2d`], noiselib's `perlin_noise_2d` at every
+/// position.
+pub fn perlin_2d_scalar(xs: &[f32], ys: &[f32], seed: u32, out: &mut [f32])
+{
+    // perlin noise never draws from the generator
+    let mut rng = UniformRandomGen::new(seed);
+    for ((x, y), out) in xs.iter().zip(ys).zip(out) {
+        *out = perlin_noise_2d(&mut rng, *x, *y, seed);
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// noiselib's 2d perlin noise at every position `xs[i]`, `ys[i]`, written to
+/// `out[i]`.
+///
+/// uses avx2 when the cpu has it, eight positions per step, and
+/// [`perlin_2d_scalar`] otherwise and for the leftover positions. the simd
+/// path does the same float operations in the same order as noiselib,
+/// without fused multiply-adds, so the results are the same bits whichever
+/// runs and worlds look the same on every cpu.
+pub fn perlin_2d(xs: &[f32], ys: &[f32], seed: u32, out: &mut [f32])
+{
+    let len = xs.len().min(ys.len()).min(out.len());
+    let done = simd_prefix(&xs[..len], &ys[..len], seed, &mut out[..len]);
+    perlin_2d_scalar(&xs[done..len], &ys[done..len], seed, &mut out[done..]);
+}
+
+/// evaluates as many whole simd steps as the cpu supports, returns how many
+/// positions were written.
+#[cfg(target_arch = "x86_64")]
+fn simd_prefix(xs: &[f32], ys: &[f32], seed: u32, out: &mut [f32]) -> usize
+{
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: the cpu supports avx2, checked above
+        unsafe { avx2::perlin_2d(xs, ys, seed, out) }
+    } else {
+        0
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn simd_prefix(_xs: &[f32], _ys: &[f32], _seed: u32, _out: &mut [f32])
+-> usize
+{
+    0
+}
+
+/// the avx2 path, a lane by lane copy of noiselib's `perlin_noise_2d`.
+#[cfg(target_arch = "x86_64")]
+mod avx2
+{
+    use super::{LANES, NOISE_PERM};
+    use std::arch::x86_64::*;
+
+    /// `NOISE_PERM[i]` for every lane.
+    ///
+    /// # Safety
+    ///
+    /// every lane of `i` must be in `0..NOISE_PERM.len()`.
+    #[target_feature(enable = "avx2")]
+    unsafe fn perm(i: __m256i) -> __m256i
+    {
+        // SAFETY: in bounds, guaranteed by the caller
+        unsafe { _mm256_i32gather_epi32::<4>(NOISE_PERM.as_ptr(), i) }
+    }
+
+    /// `x.floor() as i32` for every lane, saturating and mapping nan to 0
+    /// like the `as` cast.
+    #[target_feature(enable = "avx2")]
+    fn floor_i32(x: __m256) -> __m256i
+    {
+        let floor = _mm256_floor_ps(x);
+        let int = _mm256_cvttps_epi32(floor);
+        let too_big = _mm256_castps_si256(_mm256_cmp_ps::<_CMP_GE_OQ>(
+            floor,
+            _mm256_set1_ps(2_147_483_648.0),
+        ));
+        let int = _mm256_blendv_epi8(int, _mm256_set1_epi32(i32::MAX), too_big);
+        let nan = _mm256_castps_si256(_mm256_cmp_ps::<_CMP_UNORD_Q>(x, x));
+        _mm256_andnot_si256(nan, int)
+    }
+
+    /// noiselib's `grad2` for the wrapped lattice point `ix`, `iy`, both in
+    /// `0..=256`.
+    #[target_feature(enable = "avx2")]
+    fn grad2(ix: __m256i, iy: __m256i, fx: __m256, fy: __m256) -> __m256
+    {
+        // SAFETY: `ix <= 256` and `NOISE_PERM[ix] + iy <= 255 + 256`, both
+        // within the 512 entries
+        let h = unsafe { perm(_mm256_add_epi32(perm(ix), iy)) };
+        let h = _mm256_and_si256(h, _mm256_set1_epi32(7));
+
+        let swap = _mm256_castsi256_ps(_mm256_cmpgt_epi32(
+            h,
+            _mm256_set1_epi32(3),
+        ));
+        let u = _mm256_blendv_ps(fx, fy, swap);
+        let v = _mm256_blendv_ps(fy, fx, swap);
+
+        let odd = _mm256_castsi256_ps(_mm256_cmpeq_epi32(
+            _mm256_and_si256(h, _mm256_set1_epi32(1)),
+            _mm256_set1_epi32(1),
+        ));
+        let sign = _mm256_set1_ps(-0.0);
+        let scale = _mm256_set1_ps(1.8);
+        let u_val = _mm256_blendv_ps(
+            _mm256_mul_ps(scale, u),
+            _mm256_xor_ps(u, sign),
+            odd,
+        );
+        let v_val = _mm256_blendv_ps(
+            v,
+            _mm256_mul_ps(_mm256_set1_ps(-1.8), v),
+            odd,
+        );
+        _mm256_add_ps(u_val, v_val)
+    }
+
+    #[target_feature(enable = "avx2")]
+    fn smooth(t: __m256) -> __m256
+    {
+        let t3 = _mm256_mul_ps(_mm256_mul_ps(t, t), t);
+        let inner = _mm256_sub_ps(
+            _mm256_mul_ps(t, _mm256_set1_ps(6.0)),
+            _mm256_set1_ps(15.0),
+        );
+        let inner =
+            _mm256_add_ps(_mm256_mul_ps(t, inner), _mm256_set1_ps(10.0));
+        _mm256_mul_ps(t3, inner)
+    }
+
+    /// fills `out` for every whole group of eight positions, returns how many
+    /// positions that is.
+    #[target_feature(enable = "avx2")]
+    pub(super) fn perlin_2d(
+        xs: &[f32],
+        ys: &[f32],
+        seed: u32,
+        out: &mut [f32],
+    ) -> usize
+    {
+        let done = xs.len() / LANES * LANES;
+        let seed = (seed & 255) as usize;
+        let shift_x = _mm256_set1_epi32(NOISE_PERM[seed]);
+        let shift_y = _mm256_set1_epi32(NOISE_PERM[seed + 1]);
+        let wrap = _mm256_set1_epi32(255);
+        let one = _mm256_set1_ps(1.0);
+        let one_i = _mm256_set1_epi32(1);
+
+        for i in (0..done).step_by(LANES) {
+            // SAFETY: `i + LANES <= done`, within all three slices
+            let (x, y) = unsafe {
+                (
+                    _mm256_loadu_ps(xs.as_ptr().add(i)),
+                    _mm256_loadu_ps(ys.as_ptr().add(i)),
+                )
+            };
+
+            let (ix, iy) = (floor_i32(x), floor_i32(y));
+            let fx = _mm256_sub_ps(x, _mm256_cvtepi32_ps(ix));
+            let fy = _mm256_sub_ps(y, _mm256_cvtepi32_ps(iy));
+            let ix = _mm256_and_si256(_mm256_add_epi32(ix, shift_x), wrap);
+            let iy = _mm256_and_si256(_mm256_add_epi32(iy, shift_y), wrap);
+            let ix1 = _mm256_add_epi32(ix, one_i);
+            let iy1 = _mm256_add_epi32(iy, one_i);
+            let (fx1, fy1) = (_mm256_sub_ps(fx, one), _mm256_sub_ps(fy, one));
+
+            let w00 = grad2(ix, iy, fx, fy);
+            let w01 = grad2(ix, iy1, fx, fy1);
+            let w10 = grad2(ix1, iy, fx1, fy);
+            let w11 = grad2(ix1, iy1, fx1, fy1);
+
+            let (wx, wy) = (smooth(fx), smooth(fy));
+            let (wx1, wy1) = (_mm256_sub_ps(one, wx), _mm256_sub_ps(one, wy));
+            let near = _mm256_add_ps(
+                _mm256_mul_ps(wy1, w00),
+                _mm256_mul_ps(wy, w01),
+            );
+            let far = _mm256_add_ps(
+                _mm256_mul_ps(wy1, w10),
+                _mm256_mul_ps(wy, w11),
+            );
+            let noise = _mm256_add_ps(
+                _mm256_mul_ps(wx1, near),
+                _mm256_mul_ps(wx, far),
+            );
+            // SAFETY: as for the loads
+            unsafe { _mm256_storeu_ps(out.as_mut_ptr().add(i), noise) };
+        }
+
+        done
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn simd_matches_noiselib_bits()
+    {
+        // odd length so the scalar tail runs too, with negative, lattice,
+        // far out and nan positions. positions past `i32::MAX` overflow in
+        // noiselib itself
+        let count = 32 * 32 + 5;
+        let mut xs: Vec<f32> = (0..count)
+            .map(|i| (i as f32 - 300.0) * 0.173 + (i % 7) as f32 * 1e5)
+            .collect();
+        let mut ys: Vec<f32> = (0..count)
+            .map(|i| if i % 11 == 0 { i as f32 } else { -(i as f32) / 3.1 })
+            .collect();
+        xs[..3].copy_from_slice(&[-3e9, f32::NAN, f32::NEG_INFINITY]);
+        ys[..3].copy_from_slice(&[7.25, 1.5, -0.5]);
+
+        for seed in [0, 7, 255, 0xDEAD_BEEF] {
+            let mut fast = vec![0.0; count];
+            let mut scalar = vec![0.0; count];
+            perlin_2d(&xs, &ys, seed, &mut fast);
+            perlin_2d_scalar(&xs, &ys, seed, &mut scalar);
+
+            for (a, b) in fast.iter().zip(&scalar) {
+                assert_eq!(a.to_bits(), b.to_bits());
+            }
+        }
+    }
+}
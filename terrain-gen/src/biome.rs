@@ -0,0 +1,448 @@
+use crate::rng::Rng;
+use crate::{SeedStream, WorldCfg, block_ids, seed, terrain_noise};
+
+/// climate noise is this many times larger than the terrain noise, so biomes
+/// span several hills.
+const CLIMATE_SCALE: f32 = 2.0;
+
+/// perlin output clusters around zero, stretch it so the extremes of the
+/// climate table are actually reached.
+const CLIMATE_CONTRAST: f32 = 1.6;
+
+/// distance in blocks between the biome samples blending averages over.
+/// they sit on a fixed grid so neighboring columns share them.
+const BLEND_STEP: i32 = 4;
+
+/// keeps the surface dithering independent of other users of [`Rng`].
+const BLEND_SALT: u64 = 1;
+
+/// # category
+/// **client side processing**
+///
+/// temperature and humidity of a column, both in `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Climate
+{
+    pub temperature: f32,
+    pub humidity:    f32,
+}
+
+/// # category
+/// **client side processing**
+///
+/// the kind of terrain a column belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Biome
+{
+    Ocean,
+    Plains,
+    Desert,
+    Forest,
+    Mountains,
+    Tundra,
+}
+
+/// # category
+/// **client side processing**
+///
+/// how a biome shapes its columns.
+///
+/// heights are fractions of the world height, the column height is
+/// `base + amplitude * noise` with the noise normalized to `0.0..=1.0`.
+/// `surface` is the topmost block, `filler` the few blocks of soil under it.
+/// `shore` replaces both on beaches and under water.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiomeProfile
+{
+    pub base:      f32,
+    pub amplitude: f32,
+    pub surface:   usize,
+    pub filler:    usize,
+    pub shore:     usize,
+}
+
+impl Biome
+{
+    pub const ALL: [Biome; 6] = [
+        Biome::Ocean,
+        Biome::Plains,
+        Biome::Desert,
+        Biome::Forest,
+        Biome::Mountains,
+        Biome::Tundra,
+    ];
+
+    /// picks the biome for a climate.
+    pub fn from_climate(climate: Climate) -> Self
+    {
+        let Climate {
+            temperature,
+            humidity,
+        } = climate;
+
+        if temperature < 0.3 {
+            Biome::Tundra
+        } else if humidity > 0.75 {
+            Biome::Ocean
+        } else if temperature > 0.65 && humidity < 0.4 {
+            Biome::Desert
+        } else if humidity > 0.55 {
+            Biome::Forest
+        } else if temperature < 0.42 {
+            Biome::Mountains
+        } else {
+            Biome::Plains
+        }
+    }
+
+    pub fn profile(self) -> BiomeProfile
+    {
+        use block_ids::*;
+        let (base, amplitude, surface, filler, shore) = match self {
+            Biome::Ocean => (0.1, 0.2, SAND, SAND, SAND),
+            Biome::Plains => (0.35, 0.15, GRASS, DIRT, SAND),
+            Biome::Desert => (0.35, 0.2, SAND, SAND, SAND),
+            Biome::Forest => (0.35, 0.3, GRASS, DIRT, DIRT),
+            Biome::Mountains => (0.4, 0.6, STONE, STONE, GRAVEL),
+            Biome::Tundra => (0.35, 0.25, SNOW, DIRT, GRAVEL),
+        };
+
+        BiomeProfile {
+            base,
+            amplitude,
+            surface,
+            filler,
+            shore,
+        }
+    }
+
+    pub fn name(self) -> &'static str
+    {
+        match self {
+            Biome::Ocean => "ocean",
+            Biome::Plains => "plains",
+            Biome::Desert => "desert",
+            Biome::Forest => "forest",
+            Biome::Mountains => "mountains",
+            Biome::Tundra => "tundra",
+        }
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// how much each biome contributes to a column, the weights sum to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiomeWeights
+{
+    weights: [f32; Biome::ALL.len()],
+}
+
+impl BiomeWeights
+{
+    /// every weight on a single biome.
+    pub fn single(biome: Biome) -> Self
+    {
+        let mut weights = [0.0; Biome::ALL.len()];
+        weights[biome as usize] = 1.0;
+        Self {
+            weights,
+        }
+    }
+
+    pub fn weight(&self, biome: Biome) -> f32
+    {
+        self.weights[biome as usize]
+    }
+
+    /// weighted average of a value of every biome's profile.
+    pub fn blend(&self, value: impl Fn(BiomeProfile) -> f32) -> f32
+    {
+        Biome::ALL
+            .iter()
+            .map(|&biome| self.weight(biome) * value(biome.profile()))
+            .sum()
+    }
+
+    /// picks a biome with a chance equal to its weight, `roll` is uniform in
+    /// `0.0..1.0`.
+    pub fn pick(&self, roll: f32) -> Biome
+    {
+        let mut total = 0.0;
+        let mut picked = Biome::Plains;
+        for biome in Biome::ALL {
+            if self.weight(biome) > 0.0 {
+                // rounding can leave the total a bit short of 1
+                picked = biome;
+            }
+            total += self.weight(biome);
+            if roll < total {
+                return biome;
+            }
+        }
+        picked
+    }
+}
+
+/// temperature and humidity at the noise positions `nx[i]`, `nz[i]`.
+fn climate_fields(
+    nx: &[f32],
+    nz: &[f32],
+    cfg: &WorldCfg,
+    [temperature, humidity]: [&mut [f32]; 2],
+)
+{
+    // separate fields so temperature and humidity don't mirror each other
+    let climate_seed = cfg.sub_seed(SeedStream::Climate);
+    for (index, field) in [temperature, humidity].into_iter().enumerate() {
+        let seed = seed::noise_seed(climate_seed, index as u32);
+        terrain_noise::climate_noise_batch(nx, nz, seed, field);
+        for value in field.iter_mut() {
+            *value = ((*value * CLIMATE_CONTRAST + 1.0) / 2.0).clamp(0.0, 1.0);
+        }
+    }
+}
+
+fn climate_scale(cfg: &WorldCfg) -> f32
+{
+    cfg.world_size_b as f32 * CLIMATE_SCALE
+}
+
+/// samples temperature and humidity at every world position `xs[i]`,
+/// `zs[i]`.
+pub fn climates(xs: &[i32], zs: &[i32], cfg: &WorldCfg) -> Vec<Climate>
+{
+    let scale = climate_scale(cfg);
+    let nx: Vec<f32> = xs.iter().map(|&x| x as f32 / scale).collect();
+    let nz: Vec<f32> = zs.iter().map(|&z| z as f32 / scale).collect();
+    let mut temperature = vec![0.0; nx.len()];
+    let mut humidity = vec![0.0; nx.len()];
+    climate_fields(&nx, &nz, cfg, [&mut temperature, &mut humidity]);
+
+    temperature
+        .into_iter()
+        .zip(humidity)
+        .map(|(temperature, humidity)| Climate {
+            temperature,
+            humidity,
+        })
+        .collect()
+}
+
+/// samples temperature and humidity at world `x`, `z`.
+pub fn climate_at(x: i32, z: i32, cfg: &WorldCfg) -> Climate
+{
+    let scale = climate_scale(cfg);
+    let (nx, nz) = ([x as f32 / scale], [z as f32 / scale]);
+    let (mut temperature, mut humidity) = ([0.0], [0.0]);
+    climate_fields(&nx, &nz, cfg, [&mut temperature, &mut humidity]);
+
+    Climate {
+        temperature: temperature[0],
+        humidity:    humidity[0],
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// biome of the column containing world `x`, `z`.
+pub fn biome_at(x: i32, z: i32, cfg: &WorldCfg) -> Biome
+{
+    Biome::from_climate(climate_at(x, z, cfg))
+}
+
+/// # category
+/// **client side processing**
+///
+/// the biomes blending reads for a square area of columns, sampled in one
+/// batch so neighboring columns share them.
+pub struct BiomeGrid
+{
+    /// world position of the first sample.
+    origin: [i32; 2],
+    /// blocks between samples, [`BLEND_STEP`] or 1 when blending is off.
+    step:   i32,
+    size:   [usize; 2],
+    /// samples with `z` changing fastest.
+    biomes: Vec<Biome>,
+}
+
+impl BiomeGrid
+{
+    /// every sample [`biome_weights`] reads for the columns of the square of
+    /// `size` columns starting at world `origin_x`, `origin_z`.
+    pub fn covering(
+        origin_x: i32,
+        origin_z: i32,
+        size: usize,
+        cfg: &WorldCfg,
+    ) -> Self
+    {
+        let radius = cfg.biome_blend as i32;
+        let step = if radius == 0 { 1 } else { BLEND_STEP };
+        let first = |v: i32| (v - radius).div_euclid(step);
+        let last = |v: i32| (v + size as i32 - 1 + radius).div_euclid(step);
+        let (first_x, first_z) = (first(origin_x), first(origin_z));
+        let size = [
+            (last(origin_x) - first_x + 1) as usize,
+            (last(origin_z) - first_z + 1) as usize,
+        ];
+
+        let mut xs = Vec::with_capacity(size[0] * size[1]);
+        let mut zs = Vec::with_capacity(size[0] * size[1]);
+        for x in 0..size[0] as i32 {
+            for z in 0..size[1] as i32 {
+                xs.push((first_x + x) * step);
+                zs.push((first_z + z) * step);
+            }
+        }
+        let biomes = climates(&xs, &zs, cfg)
+            .into_iter()
+            .map(Biome::from_climate)
+            .collect();
+
+        Self {
+            origin: [first_x * step, first_z * step],
+            step,
+            size,
+            biomes,
+        }
+    }
+
+    /// biome at world `x`, `z`, the same as [`biome_at`] returns.
+    pub fn biome_at(&self, x: i32, z: i32, cfg: &WorldCfg) -> Biome
+    {
+        let (dx, dz) = (x - self.origin[0], z - self.origin[1]);
+        let on_grid = dx % self.step == 0 && dz % self.step == 0;
+        let (gx, gz) = (dx / self.step, dz / self.step);
+        let inside = (0..self.size[0] as i32).contains(&gx)
+            && (0..self.size[1] as i32).contains(&gz);
+        if on_grid && inside {
+            self.biomes[gx as usize * self.size[1] + gz as usize]
+        } else {
+            biome_at(x, z, cfg)
+        }
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// biome weights of the column at world `x`, `z`.
+///
+/// biomes are sampled on a grid around the column, out to `biome_blend`
+/// blocks, with weights falling smoothly to zero at that distance. columns
+/// further than that from a border belong to one biome only.
+pub fn biome_weights(x: i32, z: i32, cfg: &WorldCfg) -> BiomeWeights
+{
+    // the samples around one column still batch well
+    biome_weights_in(x, z, &BiomeGrid::covering(x, z, 1, cfg), cfg)
+}
+
+/// [`biome_weights`] reading its samples from a grid sampled beforehand,
+/// which must cover the column.
+pub fn biome_weights_in(
+    x: i32,
+    z: i32,
+    grid: &BiomeGrid,
+    cfg: &WorldCfg,
+) -> BiomeWeights
+{
+    let biome_of = |x, z| grid.biome_at(x, z, cfg);
+    let radius = cfg.biome_blend as i32;
+    if radius == 0 {
+        return BiomeWeights::single(biome_of(x, z));
+    }
+    let r2 = (radius * radius) as f32;
+    let grid = |v: i32| {
+        let cell = |v: i32| v.div_euclid(BLEND_STEP);
+        cell(v - radius)..=cell(v + radius)
+    };
+
+    let mut weights = [0.0; Biome::ALL.len()];
+    let mut total = 0.0;
+    for grid_x in grid(x) {
+        for grid_z in grid(z) {
+            let sample_x = grid_x * BLEND_STEP;
+            let sample_z = grid_z * BLEND_STEP;
+            let (dx, dz) = ((sample_x - x) as f32, (sample_z - z) as f32);
+            let falloff = 1.0 - (dx * dx + dz * dz) / r2;
+            if falloff <= 0.0 {
+                continue;
+            }
+            let weight = falloff * falloff;
+            weights[biome_of(sample_x, sample_z) as usize] += weight;
+            total += weight;
+        }
+    }
+
+    // radii below the grid step can miss every grid point
+    if total == 0.0 {
+        return BiomeWeights::single(biome_of(x, z));
+    }
+    BiomeWeights {
+        weights: weights.map(|weight| weight / total),
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// biome that decides the surface blocks and decorations of the column at
+/// world `x`, `z`.
+///
+/// near borders this is picked at random by the blend weights, so surfaces
+/// dither from one biome into the next instead of meeting along a line.
+pub fn surface_biome(x: i32, z: i32, weights: &BiomeWeights, cfg: &WorldCfg)
+-> Biome
+{
+    let seed = cfg.sub_seed(SeedStream::Climate);
+    weights.pick(Rng::for_cell(seed, BLEND_SALT, x, z).next_f32())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn climate_extremes_pick_expected_biomes()
+    {
+        let climate = |temperature, humidity| Climate {
+            temperature,
+            humidity,
+        };
+
+        assert_eq!(Biome::from_climate(climate(0.1, 0.5)), Biome::Tundra);
+        assert_eq!(Biome::from_climate(climate(0.9, 0.1)), Biome::Desert);
+        assert_eq!(Biome::from_climate(climate(0.6, 0.9)), Biome::Ocean);
+        assert_eq!(Biome::from_climate(climate(0.6, 0.6)), Biome::Forest);
+        assert_eq!(Biome::from_climate(climate(0.35, 0.5)), Biome::Mountains);
+        assert_eq!(Biome::from_climate(climate(0.5, 0.5)), Biome::Plains);
+    }
+
+    #[test]
+    fn blend_weights_are_normalized()
+    {
+        let hard = WorldCfg {
+            biome_blend: 0,
+            ..WorldCfg::default()
+        };
+        let cfg = WorldCfg::default();
+
+        for x in (0..512).step_by(7) {
+            let z = x / 2;
+            let single = BiomeWeights::single(biome_at(x, z, &hard));
+            assert_eq!(biome_weights(x, z, &hard), single);
+
+            let weights = biome_weights(x, z, &cfg);
+            let total: f32 =
+                Biome::ALL.iter().map(|&b| weights.weight(b)).sum();
+            assert!((total - 1.0).abs() < 1e-4);
+            let biome = surface_biome(x, z, &weights, &cfg);
+            assert!(weights.weight(biome) > 0.0);
+        }
+    }
+}
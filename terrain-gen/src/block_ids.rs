@@ -0,0 +1,81 @@
+/// empty space.
+pub const AIR: usize = 0;
+/// default solid block, everything below the soil layers.
+pub const STONE: usize = 1;
+pub const GRASS: usize = 2;
+pub const SAND: usize = 3;
+pub const SNOW: usize = 4;
+pub const DIRT: usize = 5;
+/// unbreakable floor of the world at `y = 0`.
+pub const BEDROCK: usize = 6;
+/// fills every air block below sea level.
+pub const WATER: usize = 7;
+pub const GRAVEL: usize = 8;
+pub const LOG: usize = 9;
+pub const LEAVES: usize = 10;
+pub const CACTUS: usize = 11;
+/// ores only ever replace stone, see [`ores`](crate::ores).
+pub const COAL_ORE: usize = 12;
+pub const IRON_ORE: usize = 13;
+pub const GOLD_ORE: usize = 14;
+pub const DIAMOND_ORE: usize = 15;
+/// walls, floors and ceilings of dungeons.
+pub const STONE_BRICKS: usize = 16;
+
+/// number of block ids, every id is below this.
+pub const COUNT: usize = 17;
+
+/// human readable name of a block id.
+pub fn name(block_id: usize) -> &'static str
+{
+    match block_id {
+        AIR => "air",
+        STONE => "stone",
+        GRASS => "grass",
+        SAND => "sand",
+        SNOW => "snow",
+        DIRT => "dirt",
+        BEDROCK => "bedrock",
+        WATER => "water",
+        GRAVEL => "gravel",
+        LOG => "log",
+        LEAVES => "leaves",
+        CACTUS => "cactus",
+        COAL_ORE => "coal_ore",
+        IRON_ORE => "iron_ore",
+        GOLD_ORE => "gold_ore",
+        DIAMOND_ORE => "diamond_ore",
+        STONE_BRICKS => "stone_bricks",
+        _ => "unknown",
+    }
+}
+
+/// block id for a name given by [`name`].
+pub fn by_name(name: &str) -> Option<usize>
+{
+    (0..COUNT).find(|&id| self::name(id) == name)
+}
+
+/// rgba color of a block id, used for mesh vertex colors and map previews.
+pub fn color(block_id: usize) -> [u8; 4]
+{
+    match block_id {
+        STONE => [125, 125, 125, 255],
+        GRASS => [86, 160, 60, 255],
+        SAND => [219, 204, 140, 255],
+        SNOW => [240, 245, 250, 255],
+        DIRT => [121, 85, 58, 255],
+        BEDROCK => [40, 40, 40, 255],
+        WATER => [48, 92, 200, 255],
+        GRAVEL => [140, 132, 128, 255],
+        LOG => [102, 76, 46, 255],
+        LEAVES => [52, 120, 40, 255],
+        CACTUS => [70, 140, 60, 255],
+        COAL_ORE => [50, 50, 55, 255],
+        IRON_ORE => [196, 150, 120, 255],
+        GOLD_ORE => [240, 200, 50, 255],
+        DIAMOND_ORE => [100, 220, 230, 255],
+        STONE_BRICKS => [105, 105, 112, 255],
+        _ => [255, 0, 255, 255], // unknown ids stand out
+    }
+}
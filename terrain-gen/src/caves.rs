@@ -0,0 +1,144 @@
+use crate::chunk::{BlockVolume, ColumnSample, Heightmap};
+use crate::{
+    Block, NoiseLayer, SeedStream, WorldCfg, block_ids, generator, seed,
+    terrain_noise,
+};
+
+/// horizontal size in blocks of one cave noise period.
+const CAVE_SCALE: f32 = 48.0;
+
+/// vertical size in blocks of one cave noise period. smaller than
+/// `CAVE_SCALE` so tunnels run mostly sideways.
+const CAVE_SCALE_Y: f32 = 24.0;
+
+/// how close to zero both cave fields must be for a block to be carved.
+/// larger values give wider tunnels.
+const CAVE_THRESHOLD: f32 = 0.08;
+
+/// horizontal size in blocks of one ravine noise period.
+const RAVINE_SCALE: f32 = 160.0;
+
+/// ravines are carved where the ravine noise is this close to zero.
+const RAVINE_WIDTH: f32 = 0.025;
+
+/// deepest point of a ravine, as a fraction of the column height.
+const RAVINE_FLOOR: f32 = 0.25;
+
+/// # category
+/// **client side processing**
+///
+/// spaghetti cave test for a single block.
+///
+/// two independent 3d noise fields are each near zero on a thin sheet, where
+/// both sheets cross they leave a long winding tube.
+pub fn is_cave(x: i32, y: i32, z: i32, cfg: &WorldCfg) -> bool
+{
+    let (nx, ny, nz) = (
+        x as f32 / CAVE_SCALE,
+        y as f32 / CAVE_SCALE_Y,
+        z as f32 / CAVE_SCALE,
+    );
+
+    let cave_seed = cfg.sub_seed(SeedStream::Caves);
+    let sample = |index: u32| {
+        let seed = seed::noise_seed(cave_seed, index);
+        let version = cfg.generator_version;
+        terrain_noise::cave_noise_3d(nx, ny, nz, seed, version).abs()
+    };
+
+    sample(0) < CAVE_THRESHOLD && sample(1) < CAVE_THRESHOLD
+}
+
+/// ravine noise of the column at world `x`, `z`. sampled once per column and
+/// passed to [`is_ravine`].
+pub fn ravine_at(x: i32, z: i32, cfg: &WorldCfg) -> f32
+{
+    let seed = seed::noise_seed(cfg.sub_seed(SeedStream::Ravines), 0);
+    terrain_noise::terrain_noise_2d(
+        x as f32 / RAVINE_SCALE,
+        z as f32 / RAVINE_SCALE,
+        &NoiseLayer::DEFAULT,
+        seed,
+    )
+    .abs()
+}
+
+/// ravine test for world height `y` of a column.
+///
+/// ravines follow the zero line of the ravine noise and are v shaped, widest
+/// at the surface and closing up at the floor. columns under the sea or a
+/// river are left alone so ravines don't open dry gaps beneath the water.
+pub fn is_ravine(y: i32, column: &ColumnSample, ravine: f32) -> bool
+{
+    if ravine >= RAVINE_WIDTH || column.height <= column.water_level as f32 {
+        return false;
+    }
+
+    let floor = column.height * RAVINE_FLOOR;
+    let y = y as f32;
+    if y < floor || y >= column.height {
+        return false;
+    }
+
+    let depth = (y - floor) / (column.height - floor);
+    ravine < RAVINE_WIDTH * depth.sqrt()
+}
+
+/// whether the block at world `x`, `y`, `z` is hollowed out by a cave or
+/// ravine. the bedrock floor of worlds that have one is never carved.
+pub fn is_carved(
+    x: i32,
+    y: i32,
+    z: i32,
+    column: &ColumnSample,
+    cfg: &WorldCfg,
+) -> bool
+{
+    let floor = generator::has_floor(cfg.generator_version);
+    if (floor && y <= 0) || y as f32 >= column.height {
+        return false;
+    }
+
+    is_ravine(y, column, ravine_at(x, z, cfg)) || is_cave(x, y, z, cfg)
+}
+
+/// # category
+/// **client side processing**
+///
+/// hollows out caves and ravines in a generated volume.
+///
+/// every test only depends on world coordinates and the seed, so a tunnel
+/// crossing a chunk border lines up on both sides regardless of which chunk
+/// was generated first.
+pub fn carve(volume: &mut BlockVolume, heights: &Heightmap, cfg: &WorldCfg)
+{
+    let [origin_x, origin_y, origin_z] = volume.origin;
+    let floor = generator::has_floor(cfg.generator_version);
+
+    for x in 0..volume.size_xz {
+        for z in 0..volume.size_xz {
+            let column = heights.column(x, z);
+            let (b_x, b_z) = (origin_x + x as i32, origin_z + z as i32);
+            let ravine = ravine_at(b_x, b_z, cfg);
+
+            for y in 0..volume.height {
+                let b_y = origin_y + y as i32;
+                let block_id = volume.get(x, y, z).block_id;
+                if (floor && b_y <= 0)
+                    || block_id == block_ids::AIR
+                    || block_id == block_ids::WATER
+                {
+                    continue;
+                }
+
+                if is_ravine(b_y, &column, ravine)
+                    || is_cave(b_x, b_y, b_z, cfg)
+                {
+                    volume.set(x, y, z, Block {
+                        block_id: block_ids::AIR,
+                    });
+                }
+            }
+        }
+    }
+}
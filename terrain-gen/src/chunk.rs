@@ -0,0 +1,455 @@
+use crate::biome::{self, Biome, BiomeGrid, BiomeWeights};
+use crate::rivers::RiverCarver;
+use crate::{
+    Block, SeedStream, WorldCfg, block_ids, caves, decoration, dungeons,
+    erosion, generator, ores, seed, terrain_noise,
+};
+
+/// number of filler blocks between the surface block and stone.
+pub(crate) const SOIL_DEPTH: i32 = 3;
+
+/// columns ending at most this many blocks above sea level are shores.
+pub(crate) const BEACH_HEIGHT: i32 = 2;
+
+/// fraction of the world height above which peaks are capped with snow.
+const SNOW_LINE: f32 = 0.8;
+
+/// # category
+/// **client side processing**
+///
+/// a box of generated blocks, indexed by local `x`, `y`, `z`.
+///
+/// `origin` is the world position of the block at local `(0, 0, 0)`.
+pub struct BlockVolume
+{
+    pub origin:  [i32; 3],
+    pub size_xz: usize,
+    pub height:  usize,
+    blocks:      Vec<Block>,
+}
+
+impl BlockVolume
+{
+    /// creates a volume filled with air.
+    pub fn new(origin: [i32; 3], size_xz: usize, height: usize) -> Self
+    {
+        Self {
+            origin,
+            size_xz,
+            height,
+            blocks: vec![
+                Block {
+                    block_id: 0
+                };
+                size_xz * height * size_xz
+            ],
+        }
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize
+    {
+        (x * self.height + y) * self.size_xz + z
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Block
+    {
+        self.blocks[self.index(x, y, z)]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, block: Block)
+    {
+        let idx = self.index(x, y, z);
+        self.blocks[idx] = block;
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// everything generation needs to know about a single column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColumnSample
+{
+    pub height:      f32,
+    pub biome:       Biome,
+    /// air below this height is water. the sea level, or the surface of a
+    /// river running through the column.
+    pub water_level: i32,
+}
+
+/// # category
+/// **client side processing**
+///
+/// terrain columns of a square area, sampled once per column.
+pub struct Heightmap
+{
+    pub origin_x: i32,
+    pub origin_z: i32,
+    pub size:     usize,
+    columns:      Vec<ColumnSample>,
+}
+
+impl Heightmap
+{
+    /// samples every column in the area.
+    pub fn sample(
+        origin_x: i32,
+        origin_z: i32,
+        size: usize,
+        cfg: &WorldCfg,
+    ) -> Self
+    {
+        Self {
+            origin_x,
+            origin_z,
+            size,
+            columns: column_samples(origin_x, origin_z, size, cfg),
+        }
+    }
+
+    /// fills the area from a function of world `x`, `z`, called once per
+    /// column with `z` changing fastest.
+    pub fn from_fn(
+        origin_x: i32,
+        origin_z: i32,
+        size: usize,
+        mut column: impl FnMut(i32, i32) -> ColumnSample,
+    ) -> Self
+    {
+        let mut columns = Vec::with_capacity(size * size);
+        for x in 0..size {
+            for z in 0..size {
+                columns.push(column(origin_x + x as i32, origin_z + z as i32));
+            }
+        }
+
+        Self {
+            origin_x,
+            origin_z,
+            size,
+            columns,
+        }
+    }
+
+    /// column at local `x`, `z`.
+    pub fn column(&self, x: usize, z: usize) -> ColumnSample
+    {
+        self.columns[x * self.size + z]
+    }
+
+    /// height of the column at local `x`, `z`.
+    pub fn get(&self, x: usize, z: usize) -> f32
+    {
+        self.column(x, z).height
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// where generation gets its column samples from.
+///
+/// sampling a column evaluates several noise fields, so sources may keep the
+/// results around. a [`WorldCfg`] is the plain source that samples every
+/// time, a [`ColumnCache`](crate::column_cache::ColumnCache) shares samples
+/// between chunk jobs.
+pub trait ColumnSource
+{
+    fn cfg(&self) -> &WorldCfg;
+
+    /// the column at world `x`, `z`, the same as [`column_sample`] returns.
+    fn column(&self, x: i32, z: i32) -> ColumnSample;
+
+    /// every column of a square area.
+    fn heightmap(&self, origin_x: i32, origin_z: i32, size: usize) -> Heightmap
+    {
+        Heightmap::from_fn(origin_x, origin_z, size, |x, z| self.column(x, z))
+    }
+}
+
+impl ColumnSource for WorldCfg
+{
+    fn cfg(&self) -> &WorldCfg
+    {
+        self
+    }
+
+    fn column(&self, x: i32, z: i32) -> ColumnSample
+    {
+        column_sample(x, z, self)
+    }
+
+    fn heightmap(&self, origin_x: i32, origin_z: i32, size: usize) -> Heightmap
+    {
+        Heightmap::sample(origin_x, origin_z, size, self)
+    }
+}
+
+/// amplitude weighted sum of the configured terrain layers at every world
+/// position `xs[i]`, `zs[i]`. `-1.0..=1.0` unless a layer's spline maps
+/// outside that range.
+fn terrain_noise(xs: &[i32], zs: &[i32], cfg: &WorldCfg) -> Vec<f32>
+{
+    let scale = cfg.world_size_b as f32;
+    let nx: Vec<f32> = xs.iter().map(|&x| x as f32 / scale).collect();
+    let nz: Vec<f32> = zs.iter().map(|&z| z as f32 / scale).collect();
+
+    let height_seed = cfg.sub_seed(SeedStream::Height);
+    let mut total = vec![0.0; nx.len()];
+    let mut layer_noise = vec![0.0; nx.len()];
+    let mut weight = 0.0;
+    for (i, layer) in cfg.terrain_layers.iter().enumerate() {
+        // a seed per layer so layers with similar settings don't line up
+        let seed = seed::noise_seed(height_seed, i as u32);
+        let noise = &mut layer_noise;
+        terrain_noise::sample_layer_batch(&nx, &nz, layer, seed, noise);
+        for (total, noise) in total.iter_mut().zip(&layer_noise) {
+            *total += layer.amplitude * noise;
+        }
+        weight += layer.amplitude;
+    }
+
+    total.iter_mut().for_each(|total| *total /= weight);
+    total
+}
+
+/// samples the biome and terrain height of the column at world `x`, `z`.
+pub fn column_sample(x: i32, z: i32, cfg: &WorldCfg) -> ColumnSample
+{
+    let mut column = base_column_sample(x, z, cfg);
+    finish_column(x, z, &mut column, carver(cfg).as_ref(), cfg);
+    column
+}
+
+/// # category
+/// **client side processing**
+///
+/// every column of the square of `size` columns starting at world
+/// `origin_x`, `origin_z`, with `z` changing fastest. the same samples as
+/// [`column_sample`] gives, but the noise is evaluated in batches and the
+/// biome samples are shared between neighboring columns.
+pub fn column_samples(
+    origin_x: i32,
+    origin_z: i32,
+    size: usize,
+    cfg: &WorldCfg,
+) -> Vec<ColumnSample>
+{
+    let mut columns = base_column_samples(origin_x, origin_z, size, cfg);
+    let carver = carver(cfg);
+    for (i, column) in columns.iter_mut().enumerate() {
+        let x = origin_x + (i / size) as i32;
+        let z = origin_z + (i % size) as i32;
+        finish_column(x, z, column, carver.as_ref(), cfg);
+    }
+    columns
+}
+
+/// carves the world's rivers, `None` when it has none.
+fn carver(cfg: &WorldCfg) -> Option<RiverCarver<'_>>
+{
+    (cfg.rivers.frequency > 0.0).then(|| RiverCarver::new(cfg))
+}
+
+/// applies erosion and rivers to a column straight from the noise.
+fn finish_column(
+    x: i32,
+    z: i32,
+    column: &mut ColumnSample,
+    rivers: Option<&RiverCarver>,
+    cfg: &WorldCfg,
+)
+{
+    if let Some(erosion) = &cfg.erosion {
+        column.height += erosion::height_delta(x, z, cfg, erosion);
+    }
+    if let Some(rivers) = rivers {
+        rivers.carve_column(x, z, column);
+    }
+}
+
+/// the column at world `x`, `z` straight from the noise, before erosion and
+/// rivers.
+pub(crate) fn base_column_sample(x: i32, z: i32, cfg: &WorldCfg)
+-> ColumnSample
+{
+    base_columns_at(&[x], &[z], cfg)[0]
+}
+
+/// [`base_column_sample`] of every column at world `xs[i]`, `zs[i]`, with
+/// the terrain noise of all of them evaluated in one batch.
+pub(crate) fn base_columns_at(xs: &[i32], zs: &[i32], cfg: &WorldCfg)
+-> Vec<ColumnSample>
+{
+    let noise = terrain_noise(xs, zs, cfg);
+    (0..xs.len())
+        .map(|i| {
+            let (x, z) = (xs[i], zs[i]);
+            let weights = biome::biome_weights(x, z, cfg);
+            column_from(x, z, noise[i], &weights, cfg)
+        })
+        .collect()
+}
+
+/// [`base_column_sample`] of every column of the square of `size` columns
+/// starting at world `origin_x`, `origin_z`, with `z` changing fastest. like
+/// [`column_samples`], neighboring columns share their biome samples.
+pub(crate) fn base_column_samples(
+    origin_x: i32,
+    origin_z: i32,
+    size: usize,
+    cfg: &WorldCfg,
+) -> Vec<ColumnSample>
+{
+    let mut xs = Vec::with_capacity(size * size);
+    let mut zs = Vec::with_capacity(size * size);
+    for x in 0..size as i32 {
+        for z in 0..size as i32 {
+            xs.push(origin_x + x);
+            zs.push(origin_z + z);
+        }
+    }
+
+    let noise = terrain_noise(&xs, &zs, cfg);
+    let biomes = BiomeGrid::covering(origin_x, origin_z, size, cfg);
+    (0..xs.len())
+        .map(|i| {
+            let (x, z) = (xs[i], zs[i]);
+            let weights = biome::biome_weights_in(x, z, &biomes, cfg);
+            column_from(x, z, noise[i], &weights, cfg)
+        })
+        .collect()
+}
+
+/// builds the column at world `x`, `z` from its terrain noise and biome
+/// weights.
+fn column_from(
+    x: i32,
+    z: i32,
+    noise: f32,
+    weights: &BiomeWeights,
+    cfg: &WorldCfg,
+) -> ColumnSample
+{
+    let noise_normal = (noise + 1.0) / 2.0;
+    let biome = biome::surface_biome(x, z, weights, cfg);
+    let base = weights.blend(|profile| profile.base);
+    let amplitude = weights.blend(|profile| profile.amplitude);
+    let height = (base + amplitude * noise_normal)
+        * cfg.height_scale
+        * cfg.world_height as f32;
+
+    ColumnSample {
+        height: sink_at_border(x, z, height, cfg),
+        biome,
+        water_level: cfg.sea_level as i32,
+    }
+}
+
+/// lowers `height` to the ocean floor over the falloff strip of a finite
+/// world, so the land ends in a coast rather than a cliff.
+pub(crate) fn sink_at_border(x: i32, z: i32, height: f32, cfg: &WorldCfg)
+-> f32
+{
+    let Some(border) = &cfg.border else {
+        return height;
+    };
+
+    let floor = Biome::Ocean.profile().base
+        * cfg.height_scale
+        * cfg.world_height as f32;
+    let inside = (border.distance(x, z) + 1) as f32;
+    let t = (inside / (border.falloff + 1) as f32).clamp(0.0, 1.0);
+    floor + (height - floor) * t * t * (3.0 - 2.0 * t)
+}
+
+/// terrain surface height in blocks of the column at world `x`, `z`.
+pub fn column_height(x: i32, z: i32, cfg: &WorldCfg) -> f32
+{
+    column_sample(x, z, cfg).height
+}
+
+/// picks the block at world height `y` of a sampled column.
+///
+/// from the top down a column is made of the surface block, `SOIL_DEPTH`
+/// filler blocks and stone all the way down. worlds from before generator
+/// version 3 end in a single layer of bedrock at `y = 0` with nothing beneath
+/// it. air below the water level is filled with water.
+/// columns ending under water or just above the sea use the biome's shore
+/// block instead of soil, high peaks get snow caps.
+pub fn block_in_column(column: &ColumnSample, y: i32, cfg: &WorldCfg) -> Block
+{
+    // topmost solid block of the column
+    let top = column.height.ceil() as i32 - 1;
+    let depth = top - y;
+    let profile = column.biome.profile();
+    let sea_level = cfg.sea_level as i32;
+    let snow_line = (SNOW_LINE * cfg.world_height as f32) as i32;
+    let is_shore =
+        top < sea_level + BEACH_HEIGHT || top + 1 < column.water_level;
+
+    let floor = generator::has_floor(cfg.generator_version);
+
+    let block_id = if floor && y < 0 {
+        block_ids::AIR
+    } else if floor && y == 0 {
+        block_ids::BEDROCK
+    } else if y > top && y < column.water_level {
+        block_ids::WATER
+    } else if y > top {
+        block_ids::AIR
+    } else if depth == 0 && top >= snow_line {
+        block_ids::SNOW
+    } else if depth <= SOIL_DEPTH && is_shore {
+        profile.shore
+    } else if depth == 0 {
+        profile.surface
+    } else if depth <= SOIL_DEPTH {
+        profile.filler
+    } else {
+        block_ids::STONE
+    };
+
+    Block {
+        block_id,
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// generates a whole chunk worth of blocks.
+///
+/// the 2d terrain and climate noise is evaluated once per column instead of
+/// once per block, every block in the column is then filled from that sample.
+/// pass a [`WorldCfg`] to sample from scratch, or a
+/// [`ColumnCache`](crate::column_cache::ColumnCache) to reuse samples.
+pub fn gen_chunk(
+    origin: [i32; 3],
+    size_xz: usize,
+    height: usize,
+    columns: &(impl ColumnSource + ?Sized),
+) -> BlockVolume
+{
+    let cfg = columns.cfg();
+    let heights = columns.heightmap(origin[0], origin[2], size_xz);
+    let mut volume = BlockVolume::new(origin, size_xz, height);
+
+    for x in 0..size_xz {
+        for z in 0..size_xz {
+            let column = heights.column(x, z);
+            for y in 0..height {
+                let b_y = origin[1] + y as i32;
+                let block = block_in_column(&column, b_y, cfg);
+                volume.set(x, y, z, block);
+            }
+        }
+    }
+
+    ores::place(&mut volume, cfg);
+    caves::carve(&mut volume, &heights, cfg);
+    dungeons::place(&mut volume, columns);
+    decoration::decorate(&mut volume, columns);
+
+    volume
+}
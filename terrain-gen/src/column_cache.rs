@@ -0,0 +1,228 @@
+use crate::WorldCfg;
+use crate::chunk::{ColumnSample, ColumnSource, Heightmap, column_samples};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// side length in columns of the square regions the cache samples and keeps.
+pub const REGION_SIZE: i32 = 32;
+
+/// regions kept by [`ColumnCache::new`], 12 MiB of samples.
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// columns of one region, `REGION_SIZE * REGION_SIZE` samples with `z`
+/// changing fastest.
+type Region = Arc<[ColumnSample]>;
+
+/// # category
+/// **client side processing**
+///
+/// thread-safe, bounded cache of column samples for one world.
+///
+/// columns are sampled a whole region at a time and kept until the cache is
+/// full, then the least recently used region is dropped. chunk jobs on
+/// different worker threads share one cache, so the columns that decoration
+/// looks up past a chunk's edge, the chunks stacked above and below it and
+/// chunks that are unloaded and generated again don't sample anything twice.
+pub struct ColumnCache
+{
+    cfg:      WorldCfg,
+    capacity: usize,
+    regions:  Mutex<Regions>,
+}
+
+#[derive(Default)]
+struct Regions
+{
+    /// every cached region with the tick it was last used at.
+    map:   HashMap<(i32, i32), (Region, u64)>,
+    /// the key of every cached region by the tick it was last used at, so
+    /// the least recently used one is the first.
+    used:  BTreeMap<u64, (i32, i32)>,
+    /// counts up on every lookup.
+    clock: u64,
+}
+
+impl Regions
+{
+    fn get(&mut self, key: (i32, i32)) -> Option<Region>
+    {
+        self.clock += 1;
+        let (region, used) = self.map.get_mut(&key)?;
+        self.used.remove(used);
+        *used = self.clock;
+        self.used.insert(self.clock, key);
+        Some(Arc::clone(region))
+    }
+
+    fn insert(&mut self, key: (i32, i32), region: Region, capacity: usize)
+    {
+        self.clock += 1;
+        if let Some((_, used)) = self.map.insert(key, (region, self.clock)) {
+            self.used.remove(&used);
+        }
+        self.used.insert(self.clock, key);
+
+        if self.map.len() > capacity
+            && let Some((_, oldest)) = self.used.pop_first()
+        {
+            self.map.remove(&oldest);
+        }
+    }
+}
+
+impl ColumnCache
+{
+    /// cache for the world `cfg` holding up to [`DEFAULT_CAPACITY`] regions.
+    pub fn new(cfg: WorldCfg) -> Self
+    {
+        Self::with_capacity(cfg, DEFAULT_CAPACITY)
+    }
+
+    /// cache holding up to `capacity` regions, at least one.
+    pub fn with_capacity(cfg: WorldCfg, capacity: usize) -> Self
+    {
+        Self {
+            cfg,
+            capacity: capacity.max(1),
+            regions:  Mutex::new(Regions::default()),
+        }
+    }
+
+    /// number of regions currently cached.
+    pub fn len(&self) -> usize
+    {
+        self.regions.lock().unwrap().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.len() == 0
+    }
+
+    /// the region at region coordinates `key`, sampled if it isn't cached.
+    fn region(&self, key: (i32, i32)) -> Region
+    {
+        if let Some(region) = self.regions.lock().unwrap().get(key) {
+            return region;
+        }
+
+        // sample without holding the lock so other workers aren't blocked,
+        // two workers may both sample the same region but get equal results
+        let origin_x = key.0 * REGION_SIZE;
+        let origin_z = key.1 * REGION_SIZE;
+        let size = REGION_SIZE as usize;
+        let region: Region =
+            column_samples(origin_x, origin_z, size, &self.cfg).into();
+
+        self.regions.lock().unwrap().insert(
+            key,
+            Arc::clone(&region),
+            self.capacity,
+        );
+        region
+    }
+}
+
+fn region_key(x: i32, z: i32) -> (i32, i32)
+{
+    (x.div_euclid(REGION_SIZE), z.div_euclid(REGION_SIZE))
+}
+
+fn region_index(x: i32, z: i32) -> usize
+{
+    (x.rem_euclid(REGION_SIZE) * REGION_SIZE + z.rem_euclid(REGION_SIZE))
+        as usize
+}
+
+impl ColumnSource for ColumnCache
+{
+    fn cfg(&self) -> &WorldCfg
+    {
+        &self.cfg
+    }
+
+    fn column(&self, x: i32, z: i32) -> ColumnSample
+    {
+        self.region(region_key(x, z))[region_index(x, z)]
+    }
+
+    fn heightmap(&self, origin_x: i32, origin_z: i32, size: usize) -> Heightmap
+    {
+        // look each region up once instead of once per column
+        let mut near: Vec<((i32, i32), Region)> = Vec::new();
+
+        Heightmap::from_fn(origin_x, origin_z, size, |x, z| {
+            let key = region_key(x, z);
+            let region = match near.iter().find(|(k, _)| *k == key) {
+                Some((_, region)) => region,
+                None => {
+                    near.push((key, self.region(key)));
+                    &near.last().unwrap().1
+                }
+            };
+            region[region_index(x, z)]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::chunk::{self, column_sample};
+
+    #[test]
+    fn cached_chunks_match_uncached()
+    {
+        let cfg = WorldCfg::default();
+        let cache = ColumnCache::new(cfg.clone());
+
+        // straddles region borders on both axes
+        for origin in [[-40, 0, 13], [-8, 0, 13]] {
+            let cached = chunk::gen_chunk(origin, 32, 64, &cache);
+            let uncached = chunk::gen_chunk(origin, 32, 64, &cfg);
+            for x in 0..32 {
+                for y in 0..64 {
+                    for z in 0..32 {
+                        assert_eq!(
+                            cached.get(x, y, z).block_id,
+                            uncached.get(x, y, z).block_id
+                        );
+                    }
+                }
+            }
+        }
+        assert_eq!(cache.column(-41, 7), column_sample(-41, 7, &cfg));
+    }
+
+    #[test]
+    fn cache_stays_within_capacity()
+    {
+        let cache = ColumnCache::with_capacity(WorldCfg::default(), 3);
+
+        for region_x in 0..6 {
+            cache.column(region_x * REGION_SIZE, 0);
+            assert!(cache.len() <= 3);
+        }
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn least_recently_used_region_is_dropped()
+    {
+        let cache = ColumnCache::with_capacity(WorldCfg::default(), 2);
+        let cached = |region_x: i32| {
+            let regions = cache.regions.lock().unwrap();
+            regions.map.contains_key(&(region_x, 0))
+        };
+
+        cache.column(0, 0);
+        cache.column(REGION_SIZE, 0);
+        // using the first region again makes the second the oldest
+        cache.column(0, 0);
+        cache.column(2 * REGION_SIZE, 0);
+
+        assert!(cached(0) && !cached(1) && cached(2));
+        assert_eq!(cache.regions.lock().unwrap().used.len(), 2);
+    }
+}
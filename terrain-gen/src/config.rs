@@ -0,0 +1,962 @@
+use crate::block_ids;
+use crate::generator::LATEST_VERSION;
+use crate::seed::{self, SeedStream};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::path::Path;
+
+/// # category
+/// **client side processing**
+///
+/// how the octaves of a noise layer are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoiseKind
+{
+    /// plain additive fractal noise, rolling hills.
+    #[default]
+    Fbm,
+    /// ridged multifractal, sharp crests along the zero lines of the noise.
+    /// good for mountain ranges.
+    Ridged,
+}
+
+/// # category
+/// **client side processing**
+///
+/// one layer of fractal terrain noise.
+///
+/// layers can be shaped further by warping their sample position with
+/// another noise field (`warp`), and by remapping their output through a
+/// `spline`. a low frequency layer with a spline that drops below zero makes
+/// continents and oceans, a ridged layer with a spline that only lifts the
+/// top of its range makes isolated peaks.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NoiseLayer
+{
+    pub kind:       NoiseKind,
+    /// more octaves means more detail (rocky / jagged).
+    pub octaves:    u32,
+    /// multiplier on the base sample position, higher values pack more hills
+    /// into the same area.
+    pub frequency:  f32,
+    /// weight of this layer when the layers are summed.
+    pub amplitude:  f32,
+    /// how much each successive octave contributes. 0.5 means each one is half
+    /// as much as the previous.
+    pub falloff:    f32,
+    /// how quickly the frequency increases per octave. 2.0 means the detail
+    /// level doubles each time.
+    pub lacunarity: f32,
+    /// how far in noise units the sample position is pushed around before
+    /// sampling. 0 disables domain warping.
+    pub warp:       f32,
+    /// `[input, output]` points, sorted by input, the layer output is
+    /// linearly interpolated through. empty leaves the output unchanged.
+    pub spline:     Vec<[f32; 2]>,
+}
+
+impl NoiseLayer
+{
+    /// the original rolling hills.
+    pub const DEFAULT: NoiseLayer = NoiseLayer {
+        kind:       NoiseKind::Fbm,
+        octaves:    6,
+        frequency:  1.0,
+        amplitude:  1.0,
+        falloff:    0.5,
+        lacunarity: 2.0,
+        warp:       0.0,
+        spline:     Vec::new(),
+    };
+}
+
+impl Default for NoiseLayer
+{
+    fn default() -> Self
+    {
+        Self::DEFAULT
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// which built-in [`TerrainGenerator`](crate::TerrainGenerator) a world uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeneratorKind
+{
+    /// biomes, caves and decorations on fractal perlin hills.
+    #[default]
+    Hills,
+    /// horizontal layers from `flat_layers`.
+    Superflat,
+    /// nothing but air.
+    Void,
+    /// every block id laid out on a grid.
+    Debug,
+    /// 3d noise terrain from `density`, with overhangs and floating islands.
+    Density,
+}
+
+/// # category
+/// **client side processing**
+///
+/// a layer of a superflat world, listed from the bottom up.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlatLayer
+{
+    /// block name, see [`block_ids::name`].
+    pub block:     String,
+    pub thickness: usize,
+}
+
+impl FlatLayer
+{
+    fn new(block: &str, thickness: usize) -> Self
+    {
+        Self {
+            block: block.to_string(),
+            thickness,
+        }
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// one kind of ore scattered through the stone, see [`ores`](crate::ores).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct OreCfg
+{
+    /// block name, see [`block_ids::name`].
+    pub block:      String,
+    /// lowest height in blocks a vein starts at.
+    pub min_height: i32,
+    /// highest height in blocks a vein starts at.
+    pub max_height: i32,
+    /// blocks per vein, at most [`MAX_VEIN_SIZE`].
+    pub vein_size:  u32,
+    /// average number of veins per 32x32 block area.
+    pub frequency:  f32,
+}
+
+/// largest allowed [`OreCfg::vein_size`]. bounds how far past its borders a
+/// chunk looks for veins reaching into it.
+pub const MAX_VEIN_SIZE: u32 = 32;
+
+impl OreCfg
+{
+    fn new(
+        block: &str,
+        [min_height, max_height]: [i32; 2],
+        vein_size: u32,
+        frequency: f32,
+    ) -> Self
+    {
+        Self {
+            block: block.to_string(),
+            min_height,
+            max_height,
+            vein_size,
+            frequency,
+        }
+    }
+}
+
+/// largest allowed [`WorldCfg::biome_blend`], wider blends sample too many
+/// neighbors per column.
+pub const MAX_BIOME_BLEND: u32 = 32;
+
+/// largest allowed [`DungeonCfg::max_rooms`], more don't fit in a region.
+pub const MAX_DUNGEON_ROOMS: u32 = 16;
+
+/// # category
+/// **client side processing**
+///
+/// settings of the erosion pass, see [`erosion`](crate::erosion).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ErosionCfg
+{
+    /// water droplets simulated per 64x64 block tile. more droplets cut
+    /// deeper gullies.
+    pub droplets:       u32,
+    /// longest path in blocks a droplet travels.
+    pub droplet_steps:  u32,
+    /// fraction of its free capacity a droplet picks up per step.
+    pub erode_speed:    f32,
+    /// fraction of its excess sediment a droplet drops per step.
+    pub deposit_speed:  f32,
+    /// passes of thermal erosion, each lets over-steep slopes crumble a bit.
+    pub thermal_passes: u32,
+    /// steepest stable height difference in blocks between two neighbouring
+    /// columns.
+    pub talus:          f32,
+}
+
+impl Default for ErosionCfg
+{
+    fn default() -> Self
+    {
+        Self {
+            droplets:       1500,
+            droplet_steps:  48,
+            erode_speed:    0.3,
+            deposit_speed:  0.3,
+            thermal_passes: 8,
+            talus:          1.5,
+        }
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// settings of the rivers carved into heightmap worlds, see
+/// [`rivers`](crate::rivers).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiverCfg
+{
+    /// chance that a 256x256 block area has a river spring. 0 turns rivers
+    /// off.
+    pub frequency:     f32,
+    /// lowest height in blocks above sea level a river springs at.
+    pub source_height: f32,
+    /// half width in blocks of a river at its spring. rivers widen to twice
+    /// this by the time they reach the sea.
+    pub width:         f32,
+    /// depth in blocks of the water in the middle of a river.
+    pub depth:         f32,
+}
+
+impl Default for RiverCfg
+{
+    fn default() -> Self
+    {
+        Self {
+            frequency:     0.6,
+            source_height: 6.0,
+            width:         2.0,
+            depth:         3.0,
+        }
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// settings of the underground dungeons of heightmap worlds, see
+/// [`dungeons`](crate::dungeons).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DungeonCfg
+{
+    /// chance that a 128x128 block region has a dungeon. 0 turns dungeons
+    /// off.
+    pub frequency:  f32,
+    /// most rooms a dungeon has, at least 2.
+    pub max_rooms:  u32,
+    /// lowest height in blocks of a dungeon's floor, which is never the
+    /// bedrock layer.
+    pub min_height: i32,
+    /// highest height in blocks of a dungeon's floor. rooms that would come
+    /// too close to the surface are left out.
+    pub max_height: i32,
+}
+
+impl Default for DungeonCfg
+{
+    fn default() -> Self
+    {
+        Self {
+            frequency:  0.3,
+            max_rooms:  6,
+            min_height: 4,
+            max_height: 16,
+        }
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// bounds of a finite world, a square of `2 * size` blocks centered on the
+/// origin. world columns `-size..size` are inside on both axes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BorderCfg
+{
+    /// distance in blocks from the origin to the border.
+    pub size:    u32,
+    /// width in blocks of the strip along the border over which the terrain
+    /// sinks into the ocean.
+    pub falloff: u32,
+}
+
+impl Default for BorderCfg
+{
+    fn default() -> Self
+    {
+        Self {
+            size:    1024,
+            falloff: 128,
+        }
+    }
+}
+
+impl BorderCfg
+{
+    /// columns between world `x`, `z` and the border, 0 for the outermost
+    /// columns inside and negative outside.
+    pub fn distance(&self, x: i32, z: i32) -> i32
+    {
+        let size = self.size as i32;
+        let axis = |v: i32| (v + size).min(size - 1 - v);
+        axis(x).min(axis(z))
+    }
+
+    pub fn contains(&self, x: i32, z: i32) -> bool
+    {
+        self.distance(x, z) >= 0
+    }
+
+    /// whether any column of the square of `len` blocks starting at world
+    /// `min_x`, `min_z` is inside the border.
+    pub fn overlaps(&self, min_x: i32, min_z: i32, len: i32) -> bool
+    {
+        let size = self.size as i32;
+        let axis = |min: i32| min < size && min + len > -size;
+        axis(min_x) && axis(min_z)
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// settings of the density generator, see
+/// [`density_at`](crate::density::density_at).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DensityCfg
+{
+    /// octaves of the 3d noise.
+    pub octaves:         u32,
+    /// size in blocks of one noise period.
+    pub scale:           f32,
+    /// vertical squash of the noise. above 1 flattens shapes into shelves,
+    /// below 1 stretches them into pillars.
+    pub squash:          f32,
+    /// height in blocks of the average surface, or of the middle of the
+    /// island band.
+    pub base_height:     f32,
+    /// blocks over which the height gradient grows by 1. larger values let
+    /// the noise reach further from `base_height`, giving taller overhangs.
+    pub gradient_height: f32,
+    /// added to the density everywhere. positive values fill the world up,
+    /// negative values hollow it out.
+    pub bias:            f32,
+    /// fade the ground out below `base_height` as well as above it, leaving
+    /// only floating islands.
+    pub islands:         bool,
+}
+
+impl DensityCfg
+{
+    /// wide rolling ground with overhangs and arches.
+    pub const DEFAULT: DensityCfg = DensityCfg {
+        octaves:         4,
+        scale:           96.0,
+        squash:          1.0,
+        base_height:     32.0,
+        gradient_height: 24.0,
+        bias:            0.0,
+        islands:         false,
+    };
+}
+
+impl Default for DensityCfg
+{
+    fn default() -> Self
+    {
+        Self::DEFAULT
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// world generation settings, usually loaded from a world preset file.
+///
+/// ```toml
+/// generator = "hills"
+/// generator_version = 1
+/// seed = "any text or number"
+/// world_size = 512
+/// world_height = 64
+/// sea_level = 20
+/// height_scale = 1.0
+/// biome_blend = 8
+///
+/// [[terrain_layers]]
+/// kind = "fbm"
+/// octaves = 6
+/// frequency = 1.0
+/// amplitude = 1.0
+/// falloff = 0.5
+/// lacunarity = 2.0
+/// warp = 0.0
+/// spline = [[-1.0, -1.0], [1.0, 1.0]]
+///
+/// # optional, the world is endless without this table
+/// [border]
+/// size = 1024
+/// falloff = 128
+///
+/// # optional, erosion is off without this table
+/// [erosion]
+/// droplets = 1500
+/// droplet_steps = 48
+/// erode_speed = 0.3
+/// deposit_speed = 0.3
+/// thermal_passes = 8
+/// talus = 1.5
+///
+/// # only used by the density generator
+/// [density]
+/// octaves = 4
+/// scale = 96.0
+/// squash = 1.0
+/// base_height = 32.0
+/// gradient_height = 24.0
+/// bias = 0.0
+/// islands = false
+///
+/// [rivers]
+/// frequency = 0.6
+/// source_height = 6.0
+/// width = 2.0
+/// depth = 3.0
+///
+/// [dungeons]
+/// frequency = 0.3
+/// max_rooms = 6
+/// min_height = 4
+/// max_height = 16
+///
+/// # any number of ores, the default list is replaced when one is given
+/// [[ores]]
+/// block = "iron_ore"
+/// min_height = 4
+/// max_height = 40
+/// vein_size = 8
+/// frequency = 10.0
+///
+/// # only used by the superflat generator
+/// [[flat_layers]]
+/// block = "bedrock"
+/// thickness = 1
+/// ```
+///
+/// every key is optional and falls back to [`WorldCfg::default`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorldCfg
+{
+    pub generator:         GeneratorKind,
+    /// revision of the generation code the world was created with, see
+    /// [`LATEST_VERSION`]. presets leave it out to get the latest, saved
+    /// worlds record theirs so their terrain never changes. saved worlds
+    /// without one predate versions and load as version 1, see
+    /// [`WorldCfg::load_saved`].
+    pub generator_version: u32,
+    /// horizontal size in blocks of one terrain noise period.
+    #[serde(rename = "world_size")]
+    pub world_size_b:      usize,
+    pub world_height:      usize,
+    /// every air block below this height is filled with water.
+    pub sea_level:         usize,
+    /// a toml integer, or text which is parsed with [`seed::parse`]. every
+    /// part of generation derives its own seed from this one, see
+    /// [`WorldCfg::sub_seed`].
+    #[serde(with = "seed")]
+    pub seed:              u64,
+    /// multiplier on every biome's terrain height.
+    pub height_scale:      f32,
+    /// radius in blocks over which the heights and surfaces of neighboring
+    /// biomes blend into each other. 0 gives hard borders.
+    pub biome_blend:       u32,
+    /// edge of a finite world, endless when missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border:            Option<BorderCfg>,
+    /// summed, amplitude weighted layers making up the terrain height.
+    pub terrain_layers:    Vec<NoiseLayer>,
+    /// erosion pass over the terrain heights, off when missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub erosion:           Option<ErosionCfg>,
+    /// rivers running from the highlands down to the sea.
+    pub rivers:            RiverCfg,
+    /// rooms and corridors hidden underground.
+    pub dungeons:          DungeonCfg,
+    /// ore veins placed in the stone, rarest last.
+    pub ores:              Vec<OreCfg>,
+    /// layers of the superflat generator, from the bottom up.
+    pub flat_layers:       Vec<FlatLayer>,
+    /// settings of the density generator.
+    pub density:           DensityCfg,
+}
+
+impl Default for WorldCfg
+{
+    fn default() -> Self
+    {
+        Self {
+            generator:         GeneratorKind::Hills,
+            generator_version: LATEST_VERSION,
+            world_size_b:      512,
+            world_height:      64,
+            sea_level:         20,
+            seed:              10,
+            height_scale:      1.0,
+            biome_blend:       8,
+            border:            None,
+            terrain_layers:    vec![NoiseLayer::DEFAULT],
+            erosion:           None,
+            rivers:            RiverCfg::default(),
+            dungeons:          DungeonCfg::default(),
+            ores:              vec![
+                OreCfg::new("coal_ore", [8, 64], 12, 16.0),
+                OreCfg::new("iron_ore", [4, 40], 8, 10.0),
+                OreCfg::new("gold_ore", [2, 24], 6, 3.0),
+                OreCfg::new("diamond_ore", [1, 12], 4, 1.0),
+            ],
+            flat_layers:       vec![
+                FlatLayer::new("bedrock", 1),
+                FlatLayer::new("stone", 3),
+                FlatLayer::new("dirt", 2),
+                FlatLayer::new("grass", 1),
+            ],
+            density:           DensityCfg::DEFAULT,
+        }
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// why a world preset could not be loaded.
+#[derive(Debug)]
+pub enum CfgError
+{
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    /// the file parsed but a value is out of range. holds the offending key
+    /// and what is wrong with it.
+    Invalid
+    {
+        key:    String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for CfgError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            CfgError::Io(err) => {
+                write!(f, "could not read world preset: {err}")
+            }
+            CfgError::Parse(err) => write!(f, "invalid world preset: {err}"),
+            CfgError::Invalid {
+                key,
+                reason,
+            } => write!(f, "invalid world preset: `{key}` {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for CfgError {}
+
+fn invalid(key: impl Into<String>, reason: impl Into<String>) -> CfgError
+{
+    CfgError::Invalid {
+        key:    key.into(),
+        reason: reason.into(),
+    }
+}
+
+/// also rejects nan.
+fn is_positive(value: f32) -> bool
+{
+    value > 0.0
+}
+
+impl WorldCfg
+{
+    /// seed of one part of generation.
+    pub fn sub_seed(&self, stream: SeedStream) -> u64
+    {
+        seed::sub_seed(self.seed, stream)
+    }
+
+    /// reads and validates a toml world preset.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CfgError>
+    {
+        let text = std::fs::read_to_string(path).map_err(CfgError::Io)?;
+        Self::from_toml_str(&text)
+    }
+
+    /// reads and validates the settings a world was saved with. a missing
+    /// `generator_version` means the world was saved before generators had
+    /// versions, so it is read as version 1 rather than the latest.
+    pub fn load_saved(path: impl AsRef<Path>) -> Result<Self, CfgError>
+    {
+        let text = std::fs::read_to_string(path).map_err(CfgError::Io)?;
+        Self::from_saved_toml_str(&text)
+    }
+
+    /// writes the settings as a toml world preset that loads back to the same
+    /// world.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CfgError>
+    {
+        std::fs::write(path, self.to_toml_string()).map_err(CfgError::Io)
+    }
+
+    pub fn to_toml_string(&self) -> String
+    {
+        toml::to_string(self).expect("world settings are always valid toml")
+    }
+
+    /// parses and validates a toml world preset.
+    pub fn from_toml_str(text: &str) -> Result<Self, CfgError>
+    {
+        let cfg: WorldCfg = toml::from_str(text).map_err(CfgError::Parse)?;
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    /// parses and validates the settings of a saved world, see
+    /// [`WorldCfg::load_saved`].
+    pub fn from_saved_toml_str(text: &str) -> Result<Self, CfgError>
+    {
+        let mut table: toml::Table = text.parse().map_err(CfgError::Parse)?;
+        table
+            .entry("generator_version")
+            .or_insert(toml::Value::Integer(1));
+        let cfg: WorldCfg = table.try_into().map_err(CfgError::Parse)?;
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    /// checks that every value is usable by the generator.
+    pub fn validate(&self) -> Result<(), CfgError>
+    {
+        if !(1..=LATEST_VERSION).contains(&self.generator_version) {
+            let reason = format!("must be between 1 and {LATEST_VERSION}");
+            return Err(invalid("generator_version", reason));
+        }
+        if self.world_size_b == 0 {
+            return Err(invalid("world_size", "must be greater than 0"));
+        }
+        if self.world_height == 0 {
+            return Err(invalid("world_height", "must be greater than 0"));
+        }
+        if self.sea_level > self.world_height {
+            let reason =
+                format!("must not exceed world_height ({})", self.world_height);
+            return Err(invalid("sea_level", reason));
+        }
+        if let Some(border) = &self.border {
+            if border.size == 0 {
+                return Err(invalid("border.size", "must be greater than 0"));
+            }
+            if border.falloff > border.size {
+                let reason = format!("must not exceed size ({})", border.size);
+                return Err(invalid("border.falloff", reason));
+            }
+        }
+        if !is_positive(self.height_scale) {
+            return Err(invalid("height_scale", "must be greater than 0"));
+        }
+        if self.biome_blend > MAX_BIOME_BLEND {
+            let reason = format!("must not exceed {MAX_BIOME_BLEND}");
+            return Err(invalid("biome_blend", reason));
+        }
+        if self.terrain_layers.is_empty() {
+            return Err(invalid("terrain_layers", "needs at least one layer"));
+        }
+
+        for (i, layer) in self.terrain_layers.iter().enumerate() {
+            let key = |field: &str| format!("terrain_layers[{i}].{field}");
+
+            if !(1..=16).contains(&layer.octaves) {
+                let reason = "must be between 1 and 16";
+                return Err(invalid(key("octaves"), reason));
+            }
+            if !is_positive(layer.frequency) {
+                let reason = "must be greater than 0";
+                return Err(invalid(key("frequency"), reason));
+            }
+            if !is_positive(layer.amplitude) {
+                let reason = "must be greater than 0";
+                return Err(invalid(key("amplitude"), reason));
+            }
+            if !is_positive(layer.falloff) || layer.falloff > 1.0 {
+                return Err(invalid(key("falloff"), "must be in (0, 1]"));
+            }
+            if !(1.0..).contains(&layer.lacunarity) {
+                return Err(invalid(key("lacunarity"), "must be at least 1"));
+            }
+            if !(0.0..).contains(&layer.warp) {
+                return Err(invalid(key("warp"), "must not be negative"));
+            }
+            if layer.spline.len() == 1 {
+                let reason = "needs at least two points";
+                return Err(invalid(key("spline"), reason));
+            }
+            let increasing = |pair: &[[f32; 2]]| {
+                pair[0][0].partial_cmp(&pair[1][0]) == Some(Ordering::Less)
+            };
+            if !layer.spline.windows(2).all(increasing) {
+                let reason = "points must be sorted by increasing input";
+                return Err(invalid(key("spline"), reason));
+            }
+        }
+
+        if let Some(erosion) = &self.erosion {
+            let key = |field: &str| format!("erosion.{field}");
+
+            if erosion.droplet_steps == 0 {
+                let reason = "must be greater than 0";
+                return Err(invalid(key("droplet_steps"), reason));
+            }
+            if !(0.0..=1.0).contains(&erosion.erode_speed) {
+                return Err(invalid(key("erode_speed"), "must be in [0, 1]"));
+            }
+            if !(0.0..=1.0).contains(&erosion.deposit_speed) {
+                return Err(invalid(key("deposit_speed"), "must be in [0, 1]"));
+            }
+            if !is_positive(erosion.talus) {
+                return Err(invalid(key("talus"), "must be greater than 0"));
+            }
+        }
+
+        let rivers = &self.rivers;
+        let key = |field: &str| format!("rivers.{field}");
+        if !(0.0..=1.0).contains(&rivers.frequency) {
+            return Err(invalid(key("frequency"), "must be in [0, 1]"));
+        }
+        if !(0.0..).contains(&rivers.source_height) {
+            return Err(invalid(key("source_height"), "must not be negative"));
+        }
+        if !is_positive(rivers.width) {
+            return Err(invalid(key("width"), "must be greater than 0"));
+        }
+        if !is_positive(rivers.depth) {
+            return Err(invalid(key("depth"), "must be greater than 0"));
+        }
+
+        let dungeons = &self.dungeons;
+        let key = |field: &str| format!("dungeons.{field}");
+        if !(0.0..=1.0).contains(&dungeons.frequency) {
+            return Err(invalid(key("frequency"), "must be in [0, 1]"));
+        }
+        if !(2..=MAX_DUNGEON_ROOMS).contains(&dungeons.max_rooms) {
+            let reason = format!("must be between 2 and {MAX_DUNGEON_ROOMS}");
+            return Err(invalid(key("max_rooms"), reason));
+        }
+        if dungeons.min_height < 1 {
+            return Err(invalid(key("min_height"), "must be at least 1"));
+        }
+        if dungeons.max_height < dungeons.min_height {
+            let reason = "must not be below min_height";
+            return Err(invalid(key("max_height"), reason));
+        }
+
+        let density = &self.density;
+        let key = |field: &str| format!("density.{field}");
+        if !(1..=16).contains(&density.octaves) {
+            let reason = "must be between 1 and 16";
+            return Err(invalid(key("octaves"), reason));
+        }
+        for (field, value) in [
+            ("scale", density.scale),
+            ("squash", density.squash),
+            ("gradient_height", density.gradient_height),
+        ] {
+            if !is_positive(value) {
+                return Err(invalid(key(field), "must be greater than 0"));
+            }
+        }
+
+        for (i, ore) in self.ores.iter().enumerate() {
+            let key = |field: &str| format!("ores[{i}].{field}");
+
+            if block_ids::by_name(&ore.block).is_none() {
+                let reason = format!("unknown block \"{}\"", ore.block);
+                return Err(invalid(key("block"), reason));
+            }
+            if ore.max_height < ore.min_height {
+                let reason = "must not be below min_height";
+                return Err(invalid(key("max_height"), reason));
+            }
+            if !(1..=MAX_VEIN_SIZE).contains(&ore.vein_size) {
+                let reason = format!("must be between 1 and {MAX_VEIN_SIZE}");
+                return Err(invalid(key("vein_size"), reason));
+            }
+            if !(0.0..).contains(&ore.frequency) {
+                return Err(invalid(key("frequency"), "must not be negative"));
+            }
+        }
+
+        for (i, layer) in self.flat_layers.iter().enumerate() {
+            let key = |field: &str| format!("flat_layers[{i}].{field}");
+
+            if block_ids::by_name(&layer.block).is_none() {
+                let reason = format!("unknown block \"{}\"", layer.block);
+                return Err(invalid(key("block"), reason));
+            }
+            if layer.thickness == 0 {
+                let reason = "must be greater than 0";
+                return Err(invalid(key("thickness"), reason));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn empty_preset_is_default()
+    {
+        assert_eq!(WorldCfg::from_toml_str("").unwrap(), WorldCfg::default());
+    }
+
+    #[test]
+    fn preset_overrides_and_validation()
+    {
+        let cfg = WorldCfg::from_toml_str(
+            "seed = 42\nsea_level = 30\n\n[[terrain_layers]]\noctaves = 4\n",
+        )
+        .unwrap();
+        assert_eq!(cfg.seed, 42);
+        assert_eq!(cfg.sea_level, 30);
+        assert_eq!(cfg.terrain_layers[0].octaves, 4);
+        assert_eq!(cfg.terrain_layers[0].falloff, 0.5);
+
+        let err = WorldCfg::from_toml_str("sea_level = 100").unwrap_err();
+        assert!(
+            matches!(err, CfgError::Invalid { key, .. } if key == "sea_level")
+        );
+
+        let err = WorldCfg::from_toml_str("[[terrain_layers]]\nfalloff = 2.0")
+            .unwrap_err();
+        assert!(err.to_string().contains("terrain_layers[0].falloff"));
+
+        let err = WorldCfg::from_toml_str(
+            r#"
+            generator = "superflat"
+            [[flat_layers]]
+            block = "cheese"
+            thickness = 1
+            "#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown block \"cheese\""));
+
+        let err = WorldCfg::from_toml_str("[border]\nsize = 64")
+            .unwrap_err();
+        assert!(err.to_string().contains("border.falloff"));
+
+        let err = WorldCfg::from_toml_str("generator_version = 0")
+            .unwrap_err();
+        assert!(err.to_string().contains("generator_version"));
+
+        let err = WorldCfg::from_toml_str("[erosion]\ntalus = 0.0")
+            .unwrap_err();
+        assert!(err.to_string().contains("erosion.talus"));
+
+        let err = WorldCfg::from_toml_str(
+            r#"
+            [[ores]]
+            block = "gold_ore"
+            min_height = 30
+            max_height = 10
+            vein_size = 6
+            frequency = 2.0
+            "#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("ores[0].max_height"));
+
+        let err = WorldCfg::from_toml_str("seeed = 1").unwrap_err();
+        assert!(matches!(err, CfgError::Parse(_)));
+    }
+
+    #[test]
+    fn border_bounds()
+    {
+        let border = BorderCfg {
+            size:    64,
+            falloff: 16,
+        };
+        assert_eq!(border.distance(0, 0), 63);
+        assert_eq!(border.distance(-64, 10), 0);
+        assert_eq!(border.distance(63, 10), 0);
+        assert!(!border.contains(64, 0));
+        assert!(!border.contains(0, -65));
+
+        assert!(border.overlaps(32, -64, 32));
+        assert!(border.overlaps(-96, 0, 33));
+        assert!(!border.overlaps(64, 0, 32));
+        assert!(!border.overlaps(0, -96, 32));
+    }
+
+    #[test]
+    fn seeds_from_text_and_round_trip()
+    {
+        let cfg = WorldCfg::from_toml_str("seed = \"42\"").unwrap();
+        assert_eq!(cfg.seed, 42);
+
+        let cfg = WorldCfg::from_toml_str("seed = \"my world\"").unwrap();
+        assert_eq!(cfg.seed, seed::parse("my world"));
+
+        let cfg = WorldCfg {
+            seed:           u64::MAX,
+            terrain_layers: vec![NoiseLayer {
+                kind:   NoiseKind::Ridged,
+                spline: vec![[-1.0, 0.0], [1.0, 1.0]],
+                ..NoiseLayer::DEFAULT
+            }],
+            border:         Some(BorderCfg::default()),
+            erosion:        Some(ErosionCfg::default()),
+            ..WorldCfg::default()
+        };
+        let saved = cfg.to_toml_string();
+        assert_eq!(WorldCfg::from_toml_str(&saved).unwrap(), cfg);
+        assert_eq!(WorldCfg::from_saved_toml_str(&saved).unwrap(), cfg);
+    }
+
+    #[test]
+    fn saved_worlds_without_version_stay_on_version_1()
+    {
+        let preset = WorldCfg::from_toml_str("seed = 7").unwrap();
+        assert_eq!(preset.generator_version, LATEST_VERSION);
+
+        let saved = WorldCfg::from_saved_toml_str("seed = 7").unwrap();
+        assert_eq!(saved.generator_version, 1);
+        assert_eq!(saved.seed, 7);
+    }
+}
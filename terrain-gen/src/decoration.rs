@@ -0,0 +1,288 @@
+use crate::chunk::{self, BlockVolume, ColumnSource};
+use crate::rng::Rng;
+use crate::{Biome, Block, SeedStream, WorldCfg, block_ids, caves};
+
+/// side length in blocks of a placement cell. every cell holds at most one
+/// feature.
+const CELL_SIZE: i32 = 7;
+
+/// furthest a feature reaches sideways from its origin column. chunks look
+/// this far past their borders for features that overhang into them.
+const MAX_REACH: i32 = 2;
+
+/// keeps the placement stream independent of other users of [`Rng`].
+const DECORATION_SALT: u64 = 1;
+
+/// # category
+/// **client side processing**
+///
+/// a multi-block decoration placed on the surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureKind
+{
+    Tree,
+    Pine,
+    Cactus,
+    Boulder,
+}
+
+/// # category
+/// **client side processing**
+///
+/// a feature rooted at a world position, `origin` is the first air block
+/// above the ground.
+#[derive(Debug, Clone, Copy)]
+pub struct Feature
+{
+    pub kind:   FeatureKind,
+    pub origin: [i32; 3],
+    seed:       u64,
+}
+
+impl Feature
+{
+    /// every block of the feature in world coordinates.
+    pub fn blocks(&self) -> Vec<([i32; 3], usize)>
+    {
+        let mut rng = Rng::new(self.seed);
+        let [x, y, z] = self.origin;
+        let mut out = Vec::new();
+
+        match self.kind {
+            FeatureKind::Tree => {
+                let trunk = rng.range(4, 7);
+                for dy in trunk - 2..=trunk + 1 {
+                    let radius: i32 = if dy < trunk { 2 } else { 1 };
+                    for dx in -radius..=radius {
+                        for dz in -radius..=radius {
+                            // randomly trim the corners so crowns aren't cubes
+                            let corner =
+                                dx.abs() == radius && dz.abs() == radius;
+                            if corner && (dy >= trunk || rng.chance(0.5)) {
+                                continue;
+                            }
+                            out.push((
+                                [x + dx, y + dy, z + dz],
+                                block_ids::LEAVES,
+                            ));
+                        }
+                    }
+                }
+                for dy in 0..trunk {
+                    out.push(([x, y + dy, z], block_ids::LOG));
+                }
+            }
+            FeatureKind::Pine => {
+                let trunk = rng.range(6, 9);
+                for dy in 2..=trunk {
+                    // cone narrowing towards the tip
+                    let radius = ((trunk - dy + 1) / 2).min(MAX_REACH);
+                    for dx in -radius..=radius {
+                        for dz in -radius..=radius {
+                            if dx.abs() + dz.abs() > radius + 1 {
+                                continue;
+                            }
+                            out.push((
+                                [x + dx, y + dy, z + dz],
+                                block_ids::LEAVES,
+                            ));
+                        }
+                    }
+                }
+                out.push(([x, y + trunk + 1, z], block_ids::LEAVES));
+                for dy in 0..trunk {
+                    out.push(([x, y + dy, z], block_ids::LOG));
+                }
+            }
+            FeatureKind::Cactus => {
+                for dy in 0..rng.range(2, 5) {
+                    out.push(([x, y + dy, z], block_ids::CACTUS));
+                }
+            }
+            FeatureKind::Boulder => {
+                // sunk one block into the ground
+                let radius = rng.range(1, MAX_REACH + 1);
+                let r2 = (radius * radius + radius) as f32;
+                for dx in -radius..=radius {
+                    for dy in -radius..=radius {
+                        for dz in -radius..=radius {
+                            if (dx * dx + dy * dy + dz * dz) as f32 <= r2 {
+                                out.push((
+                                    [x + dx, y + dy, z + dz],
+                                    block_ids::STONE,
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        out
+    }
+}
+
+/// feature to try in a cell of the given biome, with its chance of spawning.
+fn feature_for_biome(biome: Biome, rng: &mut Rng) -> Option<(FeatureKind, f32)>
+{
+    let roll = rng.next_f32();
+    match biome {
+        Biome::Forest => Some((FeatureKind::Tree, 0.85)),
+        Biome::Plains if roll < 0.8 => Some((FeatureKind::Tree, 0.15)),
+        Biome::Plains => Some((FeatureKind::Boulder, 0.25)),
+        Biome::Desert => Some((FeatureKind::Cactus, 0.25)),
+        Biome::Mountains if roll < 0.5 => Some((FeatureKind::Pine, 0.2)),
+        Biome::Mountains => Some((FeatureKind::Boulder, 0.3)),
+        Biome::Tundra => Some((FeatureKind::Pine, 0.2)),
+        Biome::Ocean => None,
+    }
+}
+
+/// whether a feature can grow on top of the given ground block.
+fn can_grow_on(kind: FeatureKind, ground: usize) -> bool
+{
+    match kind {
+        FeatureKind::Tree => ground == block_ids::GRASS,
+        FeatureKind::Pine => {
+            ground == block_ids::GRASS || ground == block_ids::SNOW
+        }
+        FeatureKind::Cactus => ground == block_ids::SAND,
+        FeatureKind::Boulder => ground != block_ids::WATER,
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// the feature rooted in placement cell `cell_x`, `cell_z`, if any.
+///
+/// only depends on the seed and the cell, so every chunk that overlaps the
+/// feature rebuilds exactly the same one.
+pub fn feature_in_cell(
+    cell_x: i32,
+    cell_z: i32,
+    columns: &(impl ColumnSource + ?Sized),
+) -> Option<Feature>
+{
+    let cfg = columns.cfg();
+    let seed = cfg.sub_seed(SeedStream::Decoration);
+    let mut rng = Rng::for_cell(seed, DECORATION_SALT, cell_x, cell_z);
+    let x = cell_x * CELL_SIZE + rng.range(0, CELL_SIZE);
+    let z = cell_z * CELL_SIZE + rng.range(0, CELL_SIZE);
+
+    let column = columns.column(x, z);
+    let (kind, chance) = feature_for_biome(column.biome, &mut rng)?;
+    if !rng.chance(chance) {
+        return None;
+    }
+
+    // needs dry, uncarved ground to stand on
+    let top = column.height.ceil() as i32 - 1;
+    if top < column.water_level || caves::is_carved(x, top, z, &column, cfg)
+    {
+        return None;
+    }
+    let ground = chunk::block_in_column(&column, top, cfg).block_id;
+    if !can_grow_on(kind, ground) {
+        return None;
+    }
+
+    Some(Feature {
+        kind,
+        origin: [x, top + 1, z],
+        seed: rng.next_u64(),
+    })
+}
+
+/// whether a feature block may overwrite the block already in place. trunks
+/// push through leaves, nothing replaces terrain or water.
+fn can_replace(feature_block: usize, current: usize) -> bool
+{
+    current == block_ids::AIR
+        || (current == block_ids::LEAVES && feature_block != block_ids::LEAVES)
+}
+
+/// cells whose features can reach the world columns `min..=max` on one axis.
+fn cell_range(min: i32, max: i32) -> std::ops::RangeInclusive<i32>
+{
+    (min - MAX_REACH).div_euclid(CELL_SIZE)
+        ..=(max + MAX_REACH).div_euclid(CELL_SIZE)
+}
+
+/// # category
+/// **client side processing**
+///
+/// places trees, boulders and other features into a generated volume.
+///
+/// features rooted in neighboring chunks are rebuilt here too and only their
+/// overhanging blocks written, so a tree on a chunk border comes out whole
+/// whichever side is generated first. cells are always visited in the same
+/// order, which keeps overlapping features consistent as well.
+pub fn decorate(
+    volume: &mut BlockVolume,
+    columns: &(impl ColumnSource + ?Sized),
+)
+{
+    let [origin_x, origin_y, origin_z] = volume.origin;
+    let size = volume.size_xz as i32;
+
+    for cell_x in cell_range(origin_x, origin_x + size - 1) {
+        for cell_z in cell_range(origin_z, origin_z + size - 1) {
+            let Some(feature) = feature_in_cell(cell_x, cell_z, columns)
+            else {
+                continue;
+            };
+
+            for ([x, y, z], block_id) in feature.blocks() {
+                let (lx, ly, lz) = (x - origin_x, y - origin_y, z - origin_z);
+                if lx < 0
+                    || lx >= size
+                    || lz < 0
+                    || lz >= size
+                    || ly < 0
+                    || ly >= volume.height as i32
+                {
+                    continue;
+                }
+
+                let (lx, ly, lz) = (lx as usize, ly as usize, lz as usize);
+                if can_replace(block_id, volume.get(lx, ly, lz).block_id) {
+                    volume.set(lx, ly, lz, Block {
+                        block_id,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// applies every feature touching world `x`, `y`, `z` to an undecorated
+/// block. per-block counterpart of [`decorate`].
+pub fn decorate_block(
+    x: i32,
+    y: i32,
+    z: i32,
+    base: Block,
+    cfg: &WorldCfg,
+) -> Block
+{
+    let mut block = base;
+
+    for cell_x in cell_range(x, x) {
+        for cell_z in cell_range(z, z) {
+            let Some(feature) = feature_in_cell(cell_x, cell_z, cfg) else {
+                continue;
+            };
+
+            for (pos, block_id) in feature.blocks() {
+                if pos == [x, y, z] && can_replace(block_id, block.block_id) {
+                    block = Block {
+                        block_id,
+                    };
+                }
+            }
+        }
+    }
+
+    block
+}
@@ -0,0 +1,207 @@
+use crate::chunk::{self, BEACH_HEIGHT, BlockVolume, SOIL_DEPTH};
+use crate::config::WorldCfg;
+use crate::{
+    Block, SeedStream, biome, block_ids, generator, ores, seed,
+    terrain_noise,
+};
+
+/// # category
+/// **client side processing**
+///
+/// how solid the block at world `x`, `y`, `z` is. positive is solid.
+///
+/// 3d noise makes the shapes, a height gradient pulls the density down with
+/// height so the world has ground below and sky above. where the noise beats
+/// the gradient the terrain can overhang, arch and float. in island worlds
+/// the gradient falls off both above and below `base_height`, leaving solid
+/// ground only in a band of floating islands. near the border of a finite
+/// world `base_height` sinks to the ocean floor like heightmap terrain does.
+pub fn density_at(x: i32, y: i32, z: i32, cfg: &WorldCfg) -> f32
+{
+    let density = &cfg.density;
+    let seed = seed::noise_seed(cfg.sub_seed(SeedStream::Density), 0);
+    let noise = terrain_noise::density_noise_3d(
+        x as f32 / density.scale,
+        y as f32 * density.squash / density.scale,
+        z as f32 / density.scale,
+        density.octaves,
+        seed,
+        cfg.generator_version,
+    );
+
+    let base_height = chunk::sink_at_border(x, z, density.base_height, cfg);
+    let offset = y as f32 - base_height;
+    let offset = if density.islands { offset.abs() } else { offset };
+    noise + density.bias - offset / density.gradient_height
+}
+
+/// picks a block from the solidity of a block and the number of solid blocks
+/// directly above it, `depth`.
+fn block_at(
+    y: i32,
+    solid: bool,
+    depth: i32,
+    surface_y: i32,
+    profile: biome::BiomeProfile,
+    cfg: &WorldCfg,
+) -> usize
+{
+    let sea_level = cfg.sea_level as i32;
+    let floor = generator::has_floor(cfg.generator_version);
+
+    if floor && y < 0 {
+        block_ids::AIR
+    } else if floor && y == 0 && !cfg.density.islands {
+        block_ids::BEDROCK
+    } else if !solid && y < sea_level {
+        block_ids::WATER
+    } else if !solid {
+        block_ids::AIR
+    } else if depth <= SOIL_DEPTH && surface_y < sea_level + BEACH_HEIGHT {
+        profile.shore
+    } else if depth == 0 {
+        profile.surface
+    } else if depth <= SOIL_DEPTH {
+        profile.filler
+    } else {
+        block_ids::STONE
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// fills a volume from the density field.
+///
+/// every solid block exposed to air from above gets the biome's surface
+/// block, with filler under it, so the tops of overhangs and islands are
+/// grassy too. columns are sampled a few blocks past the top of the volume
+/// so that soil depth is the same whichever chunk a column is split across.
+pub fn gen_chunk(
+    origin: [i32; 3],
+    size_xz: usize,
+    height: usize,
+    cfg: &WorldCfg,
+) -> BlockVolume
+{
+    let mut volume = BlockVolume::new(origin, size_xz, height);
+    let extra = SOIL_DEPTH as usize + 1;
+
+    for x in 0..size_xz {
+        for z in 0..size_xz {
+            let (b_x, b_z) = (origin[0] + x as i32, origin[2] + z as i32);
+            let profile = biome::biome_at(b_x, b_z, cfg).profile();
+
+            // walk down the column counting solid blocks since the last air
+            let mut depth = -1;
+            let mut surface_y = i32::MAX;
+            for y in (0..height + extra).rev() {
+                let b_y = origin[1] + y as i32;
+                let solid = density_at(b_x, b_y, b_z, cfg) > 0.0;
+                if solid {
+                    depth += 1;
+                    if depth == 0 {
+                        surface_y = b_y;
+                    }
+                } else {
+                    depth = -1;
+                }
+
+                // samples past the top only set up the depth
+                if y < height {
+                    let block_id =
+                        block_at(b_y, solid, depth, surface_y, profile, cfg);
+                    volume.set(x, y, z, Block {
+                        block_id,
+                    });
+                }
+            }
+        }
+    }
+
+    ores::place(&mut volume, cfg);
+    volume
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{BorderCfg, DensityCfg, GeneratorKind};
+
+    #[test]
+    fn density_chunks_agree_when_split()
+    {
+        let cfg = WorldCfg {
+            generator: GeneratorKind::Density,
+            ..WorldCfg::default()
+        };
+        let tall = gen_chunk([0, 0, 0], 16, 64, &cfg);
+        let upper = gen_chunk([8, 32, 0], 16, 32, &cfg);
+
+        for x in 0..8 {
+            for y in 0..32 {
+                for z in 0..16 {
+                    assert_eq!(
+                        tall.get(x + 8, y + 32, z).block_id,
+                        upper.get(x, y, z).block_id
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn islands_float()
+    {
+        let cfg = WorldCfg {
+            generator: GeneratorKind::Density,
+            sea_level: 0,
+            density:   DensityCfg {
+                base_height:     80.0,
+                gradient_height: 8.0,
+                islands:         true,
+                ..DensityCfg::DEFAULT
+            },
+            ..WorldCfg::default()
+        };
+        let volume = gen_chunk([0, 0, 0], 8, 160, &cfg);
+        let mut solid = 0;
+
+        for x in 0..8 {
+            for z in 0..8 {
+                assert_eq!(volume.get(x, 0, z).block_id, block_ids::AIR);
+                assert_eq!(volume.get(x, 159, z).block_id, block_ids::AIR);
+                solid += (0..160)
+                    .filter(|&y| volume.get(x, y, z).block_id != block_ids::AIR)
+                    .count();
+            }
+        }
+        assert!(solid > 0);
+    }
+
+    #[test]
+    fn ground_sinks_along_the_border()
+    {
+        let open = WorldCfg {
+            generator: GeneratorKind::Density,
+            ..WorldCfg::default()
+        };
+        let finite = WorldCfg {
+            border: Some(BorderCfg {
+                size:    64,
+                falloff: 32,
+            }),
+            ..open.clone()
+        };
+        let solid = |x: i32, cfg: &WorldCfg| {
+            (0..8)
+                .flat_map(|z| (0..96).map(move |y| (y, z)))
+                .filter(|&(y, z)| density_at(x, y, z, cfg) > 0.0)
+                .count()
+        };
+
+        assert!(solid(63, &finite) < solid(63, &open));
+        assert_eq!(solid(0, &finite), solid(0, &open));
+    }
+}
@@ -0,0 +1,325 @@
+use crate::chunk::{BlockVolume, ColumnSource};
+use crate::rng::Rng;
+use crate::{Block, SeedStream, block_ids};
+
+/// side length in blocks of a dungeon region. a dungeon stays inside its
+/// region, so a chunk only has to lay out the regions it overlaps.
+const REGION_SIZE: i32 = 128;
+
+/// blocks kept free between a dungeon and its region's border, so walls of
+/// dungeons in neighboring regions never touch.
+const REGION_MARGIN: i32 = 2;
+
+/// smallest and largest inner side length of a room, the upper bound is
+/// exclusive.
+const ROOM_SIZE: [i32; 2] = [5, 12];
+
+/// smallest and largest inner height of a room, the upper bound is
+/// exclusive.
+const ROOM_HEIGHT: [i32; 2] = [3, 6];
+
+/// inner height of corridors.
+const CORRIDOR_HEIGHT: i32 = 2;
+
+/// solid blocks a room keeps between its ceiling and the surface above.
+const MIN_COVER: f32 = 3.0;
+
+/// attempts at finding a free spot per wanted room.
+const ROOM_TRIES: u32 = 4;
+
+/// # category
+/// **client side processing**
+///
+/// an axis aligned box of blocks, `min` inclusive and `max` exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cuboid
+{
+    pub min: [i32; 3],
+    pub max: [i32; 3],
+}
+
+impl Cuboid
+{
+    fn contains(&self, [x, y, z]: [i32; 3]) -> bool
+    {
+        (self.min[0]..self.max[0]).contains(&x)
+            && (self.min[1]..self.max[1]).contains(&y)
+            && (self.min[2]..self.max[2]).contains(&z)
+    }
+
+    /// the box grown by `by` blocks on every side.
+    fn grown(&self, by: i32) -> Self
+    {
+        Self {
+            min: self.min.map(|v| v - by),
+            max: self.max.map(|v| v + by),
+        }
+    }
+
+    fn overlaps(&self, other: &Cuboid) -> bool
+    {
+        (0..3).all(|i| self.min[i] < other.max[i] && other.min[i] < self.max[i])
+    }
+
+    /// lowest block of the middle column, where corridors attach.
+    fn floor_center(&self) -> [i32; 3]
+    {
+        [
+            (self.min[0] + self.max[0]) / 2,
+            self.min[1],
+            (self.min[2] + self.max[2]) / 2,
+        ]
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// the layout of one dungeon, the hollow insides of its rooms and corridors.
+/// everything one block around them is walled in with stone bricks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dungeon
+{
+    pub rooms:     Vec<Cuboid>,
+    pub corridors: Vec<Cuboid>,
+}
+
+impl Dungeon
+{
+    fn hollows(&self) -> impl Iterator<Item = &Cuboid>
+    {
+        self.rooms.iter().chain(&self.corridors)
+    }
+
+    /// every block the dungeon changes lies inside this box.
+    pub fn bounds(&self) -> Cuboid
+    {
+        self.hollows().fold(self.rooms[0].grown(1), |bounds, hollow| {
+            let hollow = hollow.grown(1);
+            Cuboid {
+                min: [0, 1, 2].map(|i| bounds.min[i].min(hollow.min[i])),
+                max: [0, 1, 2].map(|i| bounds.max[i].max(hollow.max[i])),
+            }
+        })
+    }
+
+    /// the block the dungeon puts at world position `pos`, if any.
+    pub fn block_at(&self, pos: [i32; 3]) -> Option<usize>
+    {
+        if self.hollows().any(|hollow| hollow.contains(pos)) {
+            Some(block_ids::AIR)
+        } else if self.hollows().any(|hollow| hollow.grown(1).contains(pos)) {
+            Some(block_ids::STONE_BRICKS)
+        } else {
+            None
+        }
+    }
+}
+
+/// the two legs of an l-shaped corridor between the floors of two rooms,
+/// first along x, then along z.
+fn corridor(from: [i32; 3], to: [i32; 3]) -> [Cuboid; 2]
+{
+    let y = from[1];
+    let along_x = Cuboid {
+        min: [from[0].min(to[0]), y, from[2]],
+        max: [from[0].max(to[0]) + 1, y + CORRIDOR_HEIGHT, from[2] + 1],
+    };
+    let along_z = Cuboid {
+        min: [to[0], y, from[2].min(to[2])],
+        max: [to[0] + 1, y + CORRIDOR_HEIGHT, from[2].max(to[2]) + 1],
+    };
+    [along_x, along_z]
+}
+
+/// # category
+/// **client side processing**
+///
+/// the dungeon of the region `region_x`, `region_z`, if it has one.
+///
+/// rooms are dropped at random spots of the region, all on the same floor
+/// height, skipping spots that overlap another room or reach too close to
+/// the surface. each room is joined to the one placed before it by an
+/// l-shaped corridor. the layout only depends on the seed and the region,
+/// so every chunk the dungeon spans builds the same one.
+pub fn dungeon_in_region(
+    region_x: i32,
+    region_z: i32,
+    columns: &(impl ColumnSource + ?Sized),
+) -> Option<Dungeon>
+{
+    let cfg = columns.cfg();
+    let settings = &cfg.dungeons;
+    let seed = cfg.sub_seed(SeedStream::Dungeons);
+    let mut rng = Rng::for_cell(seed, 0, region_x, region_z);
+    if !rng.chance(settings.frequency) {
+        return None;
+    }
+
+    let floor = rng.range(settings.min_height, settings.max_height + 1);
+    let wanted = rng.range(2, settings.max_rooms as i32 + 1) as usize;
+    let origin_x = region_x * REGION_SIZE;
+    let origin_z = region_z * REGION_SIZE;
+
+    let mut rooms: Vec<Cuboid> = Vec::with_capacity(wanted);
+    for _ in 0..wanted as u32 * ROOM_TRIES {
+        if rooms.len() == wanted {
+            break;
+        }
+
+        let size_x = rng.range(ROOM_SIZE[0], ROOM_SIZE[1]);
+        let size_z = rng.range(ROOM_SIZE[0], ROOM_SIZE[1]);
+        let height = rng.range(ROOM_HEIGHT[0], ROOM_HEIGHT[1]);
+        let span = |size: i32| REGION_SIZE - 2 * REGION_MARGIN - 2 - size;
+        let x = origin_x + REGION_MARGIN + 1 + rng.range(0, span(size_x));
+        let z = origin_z + REGION_MARGIN + 1 + rng.range(0, span(size_z));
+        let room = Cuboid {
+            min: [x, floor + 1, z],
+            max: [x + size_x, floor + 1 + height, z + size_z],
+        };
+
+        // keep a wall's width of rock between rooms
+        if rooms.iter().any(|other| other.grown(2).overlaps(&room)) {
+            continue;
+        }
+        let ceiling = room.max[1] as f32;
+        let covered = [room.min, room.max, room.floor_center()]
+            .iter()
+            .all(|&[x, _, z]| {
+                columns.column(x, z).height - ceiling >= MIN_COVER
+            });
+        if covered {
+            rooms.push(room);
+        }
+    }
+
+    if rooms.len() < 2 {
+        return None;
+    }
+    let corridors = rooms
+        .windows(2)
+        .flat_map(|pair| {
+            corridor(pair[0].floor_center(), pair[1].floor_center())
+        })
+        .collect();
+
+    Some(Dungeon {
+        rooms,
+        corridors,
+    })
+}
+
+/// regions overlapping the world columns `min..=max` on one axis.
+fn region_range(min: i32, max: i32) -> std::ops::RangeInclusive<i32>
+{
+    min.div_euclid(REGION_SIZE)..=max.div_euclid(REGION_SIZE)
+}
+
+/// the block a dungeon puts at world `x`, `y`, `z`, if any. per-block
+/// counterpart of [`place`].
+pub fn dungeon_block(
+    x: i32,
+    y: i32,
+    z: i32,
+    columns: &(impl ColumnSource + ?Sized),
+) -> Option<usize>
+{
+    // below the lowest floor or above the highest ceiling of any dungeon
+    let settings = &columns.cfg().dungeons;
+    if y < settings.min_height || y > settings.max_height + ROOM_HEIGHT[1] {
+        return None;
+    }
+
+    let region_x = x.div_euclid(REGION_SIZE);
+    let region_z = z.div_euclid(REGION_SIZE);
+    dungeon_in_region(region_x, region_z, columns)?.block_at([x, y, z])
+}
+
+/// # category
+/// **client side processing**
+///
+/// builds the dungeons overlapping a generated volume into it.
+///
+/// every region the volume touches is laid out again and only the blocks
+/// inside the volume are written, so a dungeon spanning many chunks is the
+/// same whichever chunk is generated first.
+pub fn place(volume: &mut BlockVolume, columns: &(impl ColumnSource + ?Sized))
+{
+    let [origin_x, origin_y, origin_z] = volume.origin;
+    let size = volume.size_xz as i32;
+    let height = volume.height as i32;
+
+    for region_x in region_range(origin_x, origin_x + size - 1) {
+        for region_z in region_range(origin_z, origin_z + size - 1) {
+            let Some(dungeon) = dungeon_in_region(region_x, region_z, columns)
+            else {
+                continue;
+            };
+
+            // only visit the part of the volume the dungeon can touch
+            let bounds = dungeon.bounds();
+            let local = |axis: usize, origin: i32, len: i32| {
+                (bounds.min[axis] - origin).max(0)
+                    ..(bounds.max[axis] - origin).min(len)
+            };
+            for x in local(0, origin_x, size) {
+                for y in local(1, origin_y, height) {
+                    for z in local(2, origin_z, size) {
+                        let pos = [origin_x + x, origin_y + y, origin_z + z];
+                        let Some(block_id) = dungeon.block_at(pos) else {
+                            continue;
+                        };
+                        let (x, y, z) = (x as usize, y as usize, z as usize);
+                        volume.set(x, y, z, Block {
+                            block_id,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{DungeonCfg, WorldCfg, chunk};
+
+    #[test]
+    fn dungeons_agree_across_chunks()
+    {
+        let cfg = WorldCfg {
+            dungeons: DungeonCfg {
+                frequency: 1.0,
+                ..DungeonCfg::default()
+            },
+            ..WorldCfg::default()
+        };
+        let dungeon = (0..8)
+            .find_map(|region_x| dungeon_in_region(region_x, 0, &cfg))
+            .expect("some region has a dungeon");
+
+        // two differently aligned chunks around the first room
+        let room = dungeon.rooms[0];
+        let [x, y, z] = room.floor_center();
+        let a = chunk::gen_chunk([x - 16, 0, z - 16], 32, 32, &cfg);
+        let b = chunk::gen_chunk([x - 8, 0, z - 24], 32, 32, &cfg);
+
+        let (ax, az) = (16, 16);
+        let floor = a.get(ax, y as usize - 1, az).block_id;
+        assert_eq!(floor, block_ids::STONE_BRICKS);
+        assert_eq!(a.get(ax, y as usize, az).block_id, block_ids::AIR);
+
+        for dx in 0..24 {
+            for y in 0..32 {
+                for dz in 8..32 {
+                    assert_eq!(
+                        a.get(dx + 8, y, dz - 8).block_id,
+                        b.get(dx, y, dz).block_id
+                    );
+                }
+            }
+        }
+    }
+}
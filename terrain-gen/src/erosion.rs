@@ -0,0 +1,385 @@
+use crate::config::{ErosionCfg, WorldCfg};
+use crate::fifo_cache::FifoCache;
+use crate::rng::Rng;
+use crate::{SeedStream, chunk, generator};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, LazyLock, Mutex};
+
+/// distance in blocks between neighbouring erosion tiles. tiles are twice
+/// this wide, so every column is covered by four of them.
+const TILE_STRIDE: i32 = 32;
+
+/// side length in blocks of the part of a tile that is kept.
+const TILE_SIZE: i32 = TILE_STRIDE * 2;
+
+/// columns simulated around each tile and then thrown away, so water
+/// flowing in from outside still shapes the tile's edges.
+const TILE_PAD: i32 = 16;
+
+/// side length of the simulated grid of a tile.
+const GRID_SIZE: usize = (TILE_SIZE + 2 * TILE_PAD) as usize;
+
+/// how much of its previous direction a droplet keeps each step.
+const INERTIA: f32 = 0.05;
+
+/// sediment a droplet can carry per unit of speed, water and drop.
+const CAPACITY: f32 = 4.0;
+
+/// lets droplets on nearly flat ground still carry a little sediment.
+const MIN_CAPACITY: f32 = 0.01;
+
+/// fraction of its water a droplet loses each step.
+const EVAPORATION: f32 = 0.02;
+
+/// how quickly droplets speed up going downhill.
+const GRAVITY: f32 = 4.0;
+
+/// eroded tiles kept in memory, about 16 KiB each.
+const MAX_CACHED_TILES: usize = 256;
+
+/// eroded tiles shared by every generator in the process, keyed by
+/// [`settings_key`] and the tile position.
+static TILE_CACHE: LazyLock<Mutex<FifoCache<TileKey, Arc<[f32]>>>> =
+    LazyLock::new(|| Mutex::new(FifoCache::new(MAX_CACHED_TILES)));
+
+type TileKey = (u64, i32, i32);
+
+/// # category
+/// **client side processing**
+///
+/// change in terrain height that erosion makes to the column at world `x`,
+/// `z`.
+///
+/// the world is covered by overlapping tiles on a fixed grid, each one eroded
+/// on its own starting from the uneroded heights. a column blends the four
+/// tiles over it with weights that fall to zero at each tile's edge, so the
+/// result only depends on the position, never on which chunk asked first,
+/// and has no seams at tile borders.
+pub fn height_delta(x: i32, z: i32, cfg: &WorldCfg, erosion: &ErosionCfg)
+-> f32
+{
+    let key = settings_key(cfg, erosion);
+    let cell_x = x.div_euclid(TILE_STRIDE);
+    let cell_z = z.div_euclid(TILE_STRIDE);
+
+    let mut delta = 0.0;
+    for tile_x in cell_x - 1..=cell_x {
+        for tile_z in cell_z - 1..=cell_z {
+            let weight = tile_weight(x, tile_x) * tile_weight(z, tile_z);
+            let tile = eroded_tile(key, tile_x, tile_z, cfg, erosion);
+            let local_x = x - tile_x * TILE_STRIDE;
+            let local_z = z - tile_z * TILE_STRIDE;
+            delta += weight * tile[(local_x * TILE_SIZE + local_z) as usize];
+        }
+    }
+
+    delta
+}
+
+/// weight of tile `tile` at world coordinate `v` along one axis. hat shaped,
+/// 1 at the tile's center and 0 at its edges. the two tiles covering a
+/// coordinate always sum to 1.
+fn tile_weight(v: i32, tile: i32) -> f32
+{
+    let center = (tile * TILE_STRIDE + TILE_STRIDE) as f32;
+    let distance = (v as f32 + 0.5 - center).abs();
+    (1.0 - distance / TILE_STRIDE as f32).max(0.0)
+}
+
+/// identifies every setting the uneroded heights and the simulation depend
+/// on, so worlds with different settings never share cached tiles.
+fn settings_key(cfg: &WorldCfg, erosion: &ErosionCfg) -> u64
+{
+    let mut hasher = DefaultHasher::new();
+    hash_height_settings(cfg, &mut hasher);
+    hash_erosion_settings(erosion, &mut hasher);
+    hasher.finish()
+}
+
+/// feeds every setting the uneroded terrain heights depend on to `hasher`.
+pub(crate) fn hash_height_settings(cfg: &WorldCfg, hasher: &mut impl Hasher)
+{
+    (cfg.generator_version, cfg.seed).hash(hasher);
+    (cfg.world_size_b, cfg.world_height).hash(hasher);
+    cfg.height_scale.to_bits().hash(hasher);
+    cfg.biome_blend.hash(hasher);
+    cfg.border.hash(hasher);
+
+    for layer in &cfg.terrain_layers {
+        (layer.kind as u8, layer.octaves).hash(hasher);
+        for value in [
+            layer.frequency,
+            layer.amplitude,
+            layer.falloff,
+            layer.lacunarity,
+            layer.warp,
+        ] {
+            value.to_bits().hash(hasher);
+        }
+        for [input, output] in &layer.spline {
+            (input.to_bits(), output.to_bits()).hash(hasher);
+        }
+    }
+}
+
+/// feeds every setting of the erosion simulation to `hasher`.
+fn hash_erosion_settings(erosion: &ErosionCfg, hasher: &mut impl Hasher)
+{
+    (erosion.droplets, erosion.droplet_steps, erosion.thermal_passes)
+        .hash(hasher);
+    for value in [erosion.erode_speed, erosion.deposit_speed, erosion.talus] {
+        value.to_bits().hash(hasher);
+    }
+}
+
+/// height changes of the kept part of a tile, from the cache if possible.
+fn eroded_tile(
+    key: u64,
+    tile_x: i32,
+    tile_z: i32,
+    cfg: &WorldCfg,
+    erosion: &ErosionCfg,
+) -> Arc<[f32]>
+{
+    let key = (key, tile_x, tile_z);
+    if let Some(tile) = TILE_CACHE.lock().unwrap().get(&key) {
+        return tile;
+    }
+
+    // erode without holding the lock so other chunk jobs keep going. a tile
+    // eroded by two threads at once comes out the same both times
+    let tile: Arc<[f32]> = erode_tile(tile_x, tile_z, cfg, erosion).into();
+    TILE_CACHE.lock().unwrap().insert(key, Arc::clone(&tile));
+    tile
+}
+
+/// runs the erosion simulation for one tile and returns the height change of
+/// every kept column, indexed `x * TILE_SIZE + z`.
+fn erode_tile(
+    tile_x: i32,
+    tile_z: i32,
+    cfg: &WorldCfg,
+    erosion: &ErosionCfg,
+) -> Vec<f32>
+{
+    let min_x = tile_x * TILE_STRIDE - TILE_PAD;
+    let min_z = tile_z * TILE_STRIDE - TILE_PAD;
+
+    let heights = chunk::base_column_samples(min_x, min_z, GRID_SIZE, cfg)
+        .into_iter()
+        .map(|column| column.height)
+        .collect();
+    let mut grid = HeightGrid {
+        size: GRID_SIZE,
+        heights,
+    };
+    let base = grid.heights.clone();
+
+    let seed = cfg.sub_seed(SeedStream::Erosion);
+    let mut rng = Rng::for_cell(seed, 0, tile_x, tile_z);
+    for _ in 0..erosion.droplets {
+        grid.run_droplet(&mut rng, erosion, cfg.generator_version);
+    }
+    for _ in 0..erosion.thermal_passes {
+        grid.thermal_pass(erosion.talus);
+    }
+
+    let pad = TILE_PAD as usize;
+    let mut deltas = Vec::with_capacity((TILE_SIZE * TILE_SIZE) as usize);
+    for x in pad..pad + TILE_SIZE as usize {
+        for z in pad..pad + TILE_SIZE as usize {
+            let i = x * GRID_SIZE + z;
+            deltas.push(grid.heights[i] - base[i]);
+        }
+    }
+    deltas
+}
+
+/// square grid of column heights, indexed `x * size + z`.
+struct HeightGrid
+{
+    size:    usize,
+    heights: Vec<f32>,
+}
+
+impl HeightGrid
+{
+    fn get(&self, x: usize, z: usize) -> f32
+    {
+        self.heights[x * self.size + z]
+    }
+
+    /// bilinear height and gradient at a position inside the grid.
+    fn sample(&self, pos: [f32; 2]) -> (f32, [f32; 2])
+    {
+        let (cx, cz) = (pos[0] as usize, pos[1] as usize);
+        let (fx, fz) = (pos[0] - cx as f32, pos[1] - cz as f32);
+        let h00 = self.get(cx, cz);
+        let h10 = self.get(cx + 1, cz);
+        let h01 = self.get(cx, cz + 1);
+        let h11 = self.get(cx + 1, cz + 1);
+
+        let height = h00 * (1.0 - fx) * (1.0 - fz)
+            + h10 * fx * (1.0 - fz)
+            + h01 * (1.0 - fx) * fz
+            + h11 * fx * fz;
+        let gradient = [
+            (h10 - h00) * (1.0 - fz) + (h11 - h01) * fz,
+            (h01 - h00) * (1.0 - fx) + (h11 - h10) * fx,
+        ];
+        (height, gradient)
+    }
+
+    /// spreads `amount` over the four columns around a position, weighted by
+    /// how close the position is to each.
+    fn add(&mut self, pos: [f32; 2], amount: f32)
+    {
+        let (cx, cz) = (pos[0] as usize, pos[1] as usize);
+        let (fx, fz) = (pos[0] - cx as f32, pos[1] - cz as f32);
+        let size = self.size;
+
+        self.heights[cx * size + cz] += amount * (1.0 - fx) * (1.0 - fz);
+        self.heights[(cx + 1) * size + cz] += amount * fx * (1.0 - fz);
+        self.heights[cx * size + cz + 1] += amount * (1.0 - fx) * fz;
+        self.heights[(cx + 1) * size + cz + 1] += amount * fx * fz;
+    }
+
+    /// hydraulic erosion: a drop of water rolls downhill from a random spot,
+    /// picking up sediment while it speeds up and dropping it again where it
+    /// slows down or fills a pit. distances are measured the way generator
+    /// version `version` does.
+    fn run_droplet(&mut self, rng: &mut Rng, erosion: &ErosionCfg, version: u32)
+    {
+        let limit = (self.size - 1) as f32;
+        let mut pos = [rng.next_f32() * limit, rng.next_f32() * limit];
+        let mut dir = [0.0f32; 2];
+        let mut speed = 1.0f32;
+        let mut water = 1.0;
+        let mut sediment = 0.0;
+
+        for _ in 0..erosion.droplet_steps {
+            let (height, gradient) = self.sample(pos);
+            dir = [
+                dir[0] * INERTIA - gradient[0] * (1.0 - INERTIA),
+                dir[1] * INERTIA - gradient[1] * (1.0 - INERTIA),
+            ];
+            let length = generator::length(version, dir[0], dir[1]);
+            if length < f32::EPSILON {
+                // settled on flat ground
+                break;
+            }
+            dir = [dir[0] / length, dir[1] / length];
+
+            let next = [pos[0] + dir[0], pos[1] + dir[1]];
+            if !(0.0..limit).contains(&next[0])
+                || !(0.0..limit).contains(&next[1])
+            {
+                break;
+            }
+
+            let drop = height - self.sample(next).0;
+            let capacity = (drop * speed * water * CAPACITY).max(MIN_CAPACITY);
+            if drop < 0.0 || sediment > capacity {
+                // uphill the droplet fills the pit behind it, otherwise it
+                // drops what it can't carry
+                let amount = if drop < 0.0 {
+                    sediment.min(-drop)
+                } else {
+                    (sediment - capacity) * erosion.deposit_speed
+                };
+                sediment -= amount;
+                self.add(pos, amount);
+            } else {
+                // never dig deeper than the drop, that would leave a pit
+                let amount =
+                    ((capacity - sediment) * erosion.erode_speed).min(drop);
+                sediment += amount;
+                self.add(pos, -amount);
+            }
+
+            speed = (speed * speed + drop * GRAVITY).max(0.0).sqrt();
+            water *= 1.0 - EVAPORATION;
+            pos = next;
+        }
+    }
+
+    /// thermal erosion: wherever neighbouring columns differ by more than
+    /// `talus`, part of the excess slides down to the lower one.
+    ///
+    /// every column moves a quarter of its excess to each lower neighbour, so
+    /// it never ends up below them. moves are collected first and applied
+    /// together so the result doesn't depend on the iteration order.
+    fn thermal_pass(&mut self, talus: f32)
+    {
+        let size = self.size;
+        let mut moved = vec![0.0; self.heights.len()];
+
+        for x in 0..size {
+            for z in 0..size {
+                let i = x * size + z;
+                for n in [(x + 1, z), (x, z + 1)]
+                    .into_iter()
+                    .filter(|&(nx, nz)| nx < size && nz < size)
+                    .map(|(nx, nz)| nx * size + nz)
+                {
+                    let diff = self.heights[i] - self.heights[n];
+                    if diff.abs() > talus {
+                        let amount = (diff.abs() - talus) / 4.0 * diff.signum();
+                        moved[i] -= amount;
+                        moved[n] += amount;
+                    }
+                }
+            }
+        }
+
+        for (height, change) in self.heights.iter_mut().zip(moved) {
+            *height += change;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn tile_weights_sum_to_one()
+    {
+        for v in -100i32..100 {
+            let cell = v.div_euclid(TILE_STRIDE);
+            let sum = tile_weight(v, cell - 1) + tile_weight(v, cell);
+            assert!((sum - 1.0).abs() < 1e-6, "{v}: {sum}");
+        }
+    }
+
+    #[test]
+    fn thermal_erosion_settles_steep_slopes()
+    {
+        let size = 8;
+        let mut heights = vec![10.0; size * size];
+        heights[3 * size + 4] = 30.0;
+        let mut grid = HeightGrid {
+            size,
+            heights,
+        };
+        let total: f32 = grid.heights.iter().sum();
+
+        for _ in 0..200 {
+            grid.thermal_pass(1.0);
+        }
+
+        let steepest = (0..size - 1)
+            .flat_map(|x| (0..size - 1).map(move |z| (x, z)))
+            .map(|(x, z)| {
+                let h = grid.get(x, z);
+                let dx = (h - grid.get(x + 1, z)).abs();
+                dx.max((h - grid.get(x, z + 1)).abs())
+            })
+            .fold(0.0, f32::max);
+        assert!(steepest < 1.1, "{steepest}");
+
+        let after: f32 = grid.heights.iter().sum();
+        assert!((after - total).abs() < 1e-2);
+    }
+}
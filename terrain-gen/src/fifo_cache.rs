@@ -0,0 +1,41 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+
+/// bounded map for the process-wide generation caches, the oldest entry is
+/// dropped first once it is full.
+pub(crate) struct FifoCache<K, V>
+{
+    capacity: usize,
+    entries:  HashMap<K, V>,
+    order:    VecDeque<K>,
+}
+
+impl<K: Copy + Eq + Hash, V: Clone> FifoCache<K, V>
+{
+    pub(crate) fn new(capacity: usize) -> Self
+    {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order:   VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn get(&self, key: &K) -> Option<V>
+    {
+        self.entries.get(key).cloned()
+    }
+
+    pub(crate) fn insert(&mut self, key: K, value: V)
+    {
+        if self.entries.insert(key, value).is_some() {
+            return;
+        }
+        self.order.push_back(key);
+        if self.order.len() > self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.entries.remove(&oldest);
+        }
+    }
+}
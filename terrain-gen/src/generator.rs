@@ -0,0 +1,488 @@
+use crate::config::{GeneratorKind, WorldCfg};
+use crate::chunk::{self, ColumnSource};
+use crate::column_cache::ColumnCache;
+use crate::{Biome, Block, BlockVolume, biome, block_ids, density};
+use std::sync::Arc;
+
+/// # category
+/// **client side processing**
+///
+/// revision of the generation code new worlds are created with.
+///
+/// a world keeps generating with the version in its settings, so chunks
+/// generated after an update match the ones saved before it. any change that
+/// alters generated blocks has to bump this, name the version it arrived in
+/// with a constant like the ones below, compare `cfg.generator_version`
+/// against it to keep the old behavior for older worlds, and add golden
+/// hashes for the new version to the tests below.
+///
+/// 1. the original terrain.
+/// 2. own 3d noise for caves and density worlds, portable distances.
+/// 3. solid ground below `y = 0` instead of a bedrock floor.
+pub const LATEST_VERSION: u32 = 3;
+
+/// first version whose caves and density worlds use the crate's own 3d
+/// noise. each of its octaves has its own seed and is shifted off the
+/// others, noiselib's octaves share one seed and all vanish at the origin.
+pub(crate) const OWN_3D_NOISE: u32 = 2;
+
+/// first version measuring distances with a square root of the squares,
+/// `hypot` comes from the platform's math library and rounds differently on
+/// some platforms.
+pub(crate) const PORTABLE_DISTANCES: u32 = 2;
+
+/// first version whose ground goes on below `y = 0`, for cubic chunks that
+/// stack without a floor. caves carve it like the ground above. older
+/// versions put a bedrock layer at `y = 0` with nothing under it.
+pub(crate) const GROUND_BELOW_ZERO: u32 = 3;
+
+/// whether worlds of generator version `version` end in a bedrock floor at
+/// `y = 0`.
+pub(crate) fn has_floor(version: u32) -> bool
+{
+    version < GROUND_BELOW_ZERO
+}
+
+/// length of the vector `x`, `y`, measured the way generator version
+/// `version` does.
+pub(crate) fn length(version: u32, x: f32, y: f32) -> f32
+{
+    if version >= PORTABLE_DISTANCES {
+        (x * x + y * y).sqrt()
+    } else {
+        x.hypot(y)
+    }
+}
+
+/// y level the debug generator lays its block grid on.
+const DEBUG_LAYER: i32 = 4;
+
+/// number of block ids per row of the debug grid.
+const DEBUG_ROW: i32 = 4;
+
+/// # category
+/// **client side processing**
+///
+/// a source of terrain for a world.
+///
+/// generators are shared between the chunk worker threads, so everything
+/// they produce has to be a pure function of the world position.
+pub trait TerrainGenerator: Send + Sync
+{
+    /// fills a chunk sized volume whose lowest corner is at world `origin`.
+    fn gen_chunk(
+        &self,
+        origin: [i32; 3],
+        size_xz: usize,
+        height: usize,
+    ) -> BlockVolume;
+
+    /// biome of the column at world `x`, `z`, if the generator has biomes.
+    fn biome_at(&self, _x: i32, _z: i32) -> Option<Biome>
+    {
+        None
+    }
+
+    fn name(&self) -> &'static str;
+}
+
+/// # category
+/// **client side processing**
+///
+/// builds the generator selected by a world preset, at the preset's
+/// generator version. every version shares the generators, the parts of
+/// generation that changed between versions check `cfg.generator_version`
+/// and keep the old code for older worlds.
+///
+/// panics on versions this build doesn't know, [`WorldCfg::validate`]
+/// rejects them.
+pub fn from_cfg(cfg: &WorldCfg) -> Arc<dyn TerrainGenerator>
+{
+    match cfg.generator_version {
+        1..=LATEST_VERSION => {}
+        version => panic!("unsupported generator version {version}"),
+    }
+
+    match cfg.generator {
+        GeneratorKind::Hills => Arc::new(HillsGenerator::new(cfg.clone())),
+        GeneratorKind::Superflat => {
+            Arc::new(SuperflatGenerator::from_cfg(cfg))
+        }
+        GeneratorKind::Void => Arc::new(VoidGenerator),
+        GeneratorKind::Debug => Arc::new(DebugGenerator),
+        GeneratorKind::Density => {
+            Arc::new(DensityGenerator::new(cfg.clone()))
+        }
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// the default world: biomes on fractal perlin hills, with caves, water and
+/// decorations. column samples are shared between chunk jobs through a
+/// [`ColumnCache`].
+pub struct HillsGenerator
+{
+    columns: ColumnCache,
+}
+
+impl HillsGenerator
+{
+    pub fn new(cfg: WorldCfg) -> Self
+    {
+        Self {
+            columns: ColumnCache::new(cfg),
+        }
+    }
+}
+
+impl TerrainGenerator for HillsGenerator
+{
+    fn gen_chunk(
+        &self,
+        origin: [i32; 3],
+        size_xz: usize,
+        height: usize,
+    ) -> BlockVolume
+    {
+        chunk::gen_chunk(origin, size_xz, height, &self.columns)
+    }
+
+    fn biome_at(&self, x: i32, z: i32) -> Option<Biome>
+    {
+        Some(self.columns.column(x, z).biome)
+    }
+
+    fn name(&self) -> &'static str
+    {
+        "hills"
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// terrain shaped by a 3d density field, see [`density::density_at`].
+/// allows overhangs, arches and floating islands that a heightmap can't.
+pub struct DensityGenerator
+{
+    cfg: WorldCfg,
+}
+
+impl DensityGenerator
+{
+    pub fn new(cfg: WorldCfg) -> Self
+    {
+        Self {
+            cfg,
+        }
+    }
+}
+
+impl TerrainGenerator for DensityGenerator
+{
+    fn gen_chunk(
+        &self,
+        origin: [i32; 3],
+        size_xz: usize,
+        height: usize,
+    ) -> BlockVolume
+    {
+        density::gen_chunk(origin, size_xz, height, &self.cfg)
+    }
+
+    fn biome_at(&self, x: i32, z: i32) -> Option<Biome>
+    {
+        Some(biome::biome_at(x, z, &self.cfg))
+    }
+
+    fn name(&self) -> &'static str
+    {
+        "density"
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// flat horizontal layers, the same in every column.
+pub struct SuperflatGenerator
+{
+    /// block id of every layer from `y = 0` upwards.
+    layers: Vec<usize>,
+}
+
+impl SuperflatGenerator
+{
+    /// `layers` lists `(block id, thickness)` from the bottom up.
+    pub fn new(layers: &[(usize, usize)]) -> Self
+    {
+        Self {
+            layers: layers
+                .iter()
+                .flat_map(|&(block_id, thickness)| {
+                    std::iter::repeat_n(block_id, thickness)
+                })
+                .collect(),
+        }
+    }
+
+    /// uses the preset's `flat_layers`. unknown block names become air, the
+    /// preset is expected to be validated already.
+    pub fn from_cfg(cfg: &WorldCfg) -> Self
+    {
+        let layers: Vec<(usize, usize)> = cfg
+            .flat_layers
+            .iter()
+            .map(|layer| {
+                let block_id =
+                    block_ids::by_name(&layer.block).unwrap_or(block_ids::AIR);
+                (block_id, layer.thickness)
+            })
+            .collect();
+
+        Self::new(&layers)
+    }
+}
+
+impl TerrainGenerator for SuperflatGenerator
+{
+    fn gen_chunk(
+        &self,
+        origin: [i32; 3],
+        size_xz: usize,
+        height: usize,
+    ) -> BlockVolume
+    {
+        let mut volume = BlockVolume::new(origin, size_xz, height);
+
+        for y in 0..height {
+            let Ok(layer) = usize::try_from(origin[1] + y as i32) else {
+                continue;
+            };
+            let Some(&block_id) = self.layers.get(layer) else {
+                continue;
+            };
+
+            for x in 0..size_xz {
+                for z in 0..size_xz {
+                    volume.set(x, y, z, Block {
+                        block_id,
+                    });
+                }
+            }
+        }
+
+        volume
+    }
+
+    fn name(&self) -> &'static str
+    {
+        "superflat"
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// an empty world.
+pub struct VoidGenerator;
+
+impl TerrainGenerator for VoidGenerator
+{
+    fn gen_chunk(
+        &self,
+        origin: [i32; 3],
+        size_xz: usize,
+        height: usize,
+    ) -> BlockVolume
+    {
+        BlockVolume::new(origin, size_xz, height)
+    }
+
+    fn name(&self) -> &'static str
+    {
+        "void"
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// every block id once, on a checkerboard grid at `DEBUG_LAYER` starting at
+/// the world origin. handy for checking how new blocks look.
+pub struct DebugGenerator;
+
+impl DebugGenerator
+{
+    /// block at world `x`, `y`, `z`.
+    pub fn block_at(x: i32, y: i32, z: i32) -> Block
+    {
+        // blocks sit on even coordinates with air in between
+        let on_grid = y == DEBUG_LAYER
+            && (0..DEBUG_ROW * 2).contains(&x)
+            && z >= 0
+            && x % 2 == 0
+            && z % 2 == 0;
+        let index = ((z / 2) * DEBUG_ROW + x / 2) as usize;
+
+        // id 0 is air, it would just be a gap
+        let block_id = if on_grid && index + 1 < block_ids::COUNT {
+            index + 1
+        } else {
+            block_ids::AIR
+        };
+
+        Block {
+            block_id,
+        }
+    }
+}
+
+impl TerrainGenerator for DebugGenerator
+{
+    fn gen_chunk(
+        &self,
+        origin: [i32; 3],
+        size_xz: usize,
+        height: usize,
+    ) -> BlockVolume
+    {
+        let mut volume = BlockVolume::new(origin, size_xz, height);
+
+        for x in 0..size_xz {
+            for y in 0..height {
+                for z in 0..size_xz {
+                    let block = Self::block_at(
+                        origin[0] + x as i32,
+                        origin[1] + y as i32,
+                        origin[2] + z as i32,
+                    );
+                    volume.set(x, y, z, block);
+                }
+            }
+        }
+
+        volume
+    }
+
+    fn name(&self) -> &'static str
+    {
+        "debug"
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::config::{ErosionCfg, RiverCfg};
+
+    /// hash of every block id in the [`GOLDEN_REGIONS`] generated by `kind`
+    /// at `version`, per version and generator. a hash changing means worlds
+    /// of that version generate different terrain than they used to.
+    const GOLDEN: [(u32, GeneratorKind, u64); 15] = [
+        (1, GeneratorKind::Hills, 0x8DC5_A02C_0785_AD89),
+        (1, GeneratorKind::Density, 0x4D0B_3634_18A2_55C6),
+        (1, GeneratorKind::Superflat, 0x9D43_5D15_435F_0BA5),
+        (1, GeneratorKind::Void, 0x431E_45C7_E067_9325),
+        (1, GeneratorKind::Debug, 0x7403_979D_DE5A_4735),
+        (2, GeneratorKind::Hills, 0x4135_980A_A91D_0665),
+        (2, GeneratorKind::Density, 0x30EB_6128_5ACC_C0B8),
+        (2, GeneratorKind::Superflat, 0x9D43_5D15_435F_0BA5),
+        (2, GeneratorKind::Void, 0x431E_45C7_E067_9325),
+        (2, GeneratorKind::Debug, 0x7403_979D_DE5A_4735),
+        (3, GeneratorKind::Hills, 0x7EDC_4042_5753_CCBE),
+        (3, GeneratorKind::Density, 0xFF22_59D9_70FE_BD38),
+        (3, GeneratorKind::Superflat, 0x9D43_5D15_435F_0BA5),
+        (3, GeneratorKind::Void, 0x431E_45C7_E067_9325),
+        (3, GeneratorKind::Debug, 0x7403_979D_DE5A_4735),
+    ];
+
+    /// origin and height of the columns hashed for [`GOLDEN`], 8 by 8 blocks
+    /// each. from below the ground up to the sky at the origin, across a
+    /// river and far out.
+    const GOLDEN_REGIONS: [([i32; 3], usize); 3] = [
+        ([0, -16, 0], 64),
+        ([-300, 16, 176], 48),
+        ([-3000, 0, 1000], 64),
+    ];
+
+    /// fnv-1a over the block ids of the [`GOLDEN_REGIONS`].
+    fn chunk_hash(generator: &dyn TerrainGenerator) -> u64
+    {
+        let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+        for (origin, height) in GOLDEN_REGIONS {
+            let volume = generator.gen_chunk(origin, 8, height);
+            for x in 0..8 {
+                for y in 0..height {
+                    for z in 0..8 {
+                        hash ^= volume.get(x, y, z).block_id as u64;
+                        hash = hash.wrapping_mul(0x0100_0000_01B3);
+                    }
+                }
+            }
+        }
+        hash
+    }
+
+    #[test]
+    fn versions_generate_golden_chunks()
+    {
+        for (generator_version, kind, expected) in GOLDEN {
+            // light erosion, and fewer rivers with a closer sea to trace them
+            // to, keep the caches filled for each version quick to build
+            let cfg = WorldCfg {
+                generator: kind,
+                generator_version,
+                seed:      0x5EED,
+                sea_level: 28,
+                erosion:   Some(ErosionCfg {
+                    droplets:       200,
+                    thermal_passes: 2,
+                    ..ErosionCfg::default()
+                }),
+                rivers:    RiverCfg {
+                    frequency: 0.3,
+                    ..RiverCfg::default()
+                },
+                ..WorldCfg::default()
+            };
+            let hash = chunk_hash(from_cfg(&cfg).as_ref());
+            let label = format!("v{generator_version} {kind:?}");
+            assert_eq!(hash, expected, "{label} hashes to {hash:#x}");
+        }
+    }
+
+    #[test]
+    fn superflat_stacks_layers()
+    {
+        let generator = SuperflatGenerator::new(&[
+            (block_ids::BEDROCK, 1),
+            (block_ids::DIRT, 2),
+        ]);
+        let volume = generator.gen_chunk([-32, 0, 0], 32, 64);
+
+        assert_eq!(volume.get(5, 0, 7).block_id, block_ids::BEDROCK);
+        assert_eq!(volume.get(5, 1, 7).block_id, block_ids::DIRT);
+        assert_eq!(volume.get(5, 2, 7).block_id, block_ids::DIRT);
+        assert_eq!(volume.get(5, 3, 7).block_id, block_ids::AIR);
+    }
+
+    #[test]
+    fn debug_world_shows_every_block_once()
+    {
+        let volume = DebugGenerator.gen_chunk([0, 0, 0], 32, 64);
+        let mut seen = [0; block_ids::COUNT];
+        for x in 0..32 {
+            for y in 0..64 {
+                for z in 0..32 {
+                    seen[volume.get(x, y, z).block_id] += 1;
+                }
+            }
+        }
+
+        assert!(seen[1..].iter().all(|&count| count == 1));
+    }
+}
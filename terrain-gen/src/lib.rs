@@ -1,5 +1,33 @@
+pub mod batch_noise;
+pub mod biome;
+pub mod block_ids;
+pub mod caves;
+pub mod chunk;
+pub mod column_cache;
+pub mod config;
+pub mod decoration;
+pub mod density;
+pub mod dungeons;
+pub mod erosion;
+mod fifo_cache;
+pub mod generator;
+pub mod ores;
+pub mod rivers;
+pub mod rng;
+pub mod seed;
 mod terrain_noise;
-use noiselib;
+
+pub use biome::{Biome, biome_at};
+pub use chunk::{
+    BlockVolume, ColumnSample, ColumnSource, Heightmap, column_height,
+    column_sample, column_samples, gen_chunk,
+};
+pub use config::{
+    BorderCfg, CfgError, DensityCfg, DungeonCfg, ErosionCfg, FlatLayer,
+    GeneratorKind, NoiseKind, NoiseLayer, OreCfg, RiverCfg, WorldCfg,
+};
+pub use generator::TerrainGenerator;
+pub use seed::SeedStream;
 
 /// # category
 /// **client side processing**
@@ -11,34 +39,30 @@ pub struct Block
     pub block_id: usize,
 }
 
-pub struct WorldCfg
-{
-    pub world_size_b: usize,
-    pub world_height: usize,
-    pub seed:         u32,
-}
-
+/// generates a single block. prefer [`gen_chunk`] when filling whole chunks,
+/// it samples the noise once per column instead of once per block.
 pub fn block_gen(x: i32, y: i32, z: i32, cfg: WorldCfg) -> Block
 {
-    let mut rng = noiselib::prelude::UniformRandomGen::new(cfg.seed);
-    let perlin_out = terrain_noise::terrain_noise_2d(
-        &mut rng,
-        (x as f32) / cfg.world_size_b as f32,
-        (z as f32) / cfg.world_size_b as f32,
-        cfg.seed,
-    );
-
-    let perlin_out_normal = (perlin_out + 1.0) / 2.0;
-    let block_id = if (perlin_out_normal * (cfg.world_height as f32)) > y as f32
-    {
-        1
+    let column = column_sample(x, z, &cfg);
+    let base = if let Some(block_id) = dungeons::dungeon_block(x, y, z, &cfg) {
+        Block {
+            block_id,
+        }
+    } else if caves::is_carved(x, y, z, &column, &cfg) {
+        Block {
+            block_id: block_ids::AIR,
+        }
     } else {
-        0
+        let block = chunk::block_in_column(&column, y, &cfg);
+        let ore = (block.block_id == block_ids::STONE)
+            .then(|| ores::ore_at(x, y, z, &cfg))
+            .flatten();
+        ore.map_or(block, |block_id| Block {
+            block_id,
+        })
     };
 
-    return Block {
-        block_id,
-    };
+    decoration::decorate_block(x, y, z, base, &cfg)
 }
 
 pub fn add(left: u64, right: u64) -> u64
@@ -57,4 +81,153 @@ mod tests
         let result = add(2, 2);
         assert_eq!(result, 4);
     }
+
+    #[test]
+    fn gen_chunk_matches_block_gen()
+    {
+        let cfg = WorldCfg::default();
+        let volume = gen_chunk([-32, 0, 64], 32, 64, &cfg);
+
+        // a grid of whole columns, including the chunk's edges, keeps the
+        // block by block generation quick
+        let sampled = [0, 7, 15, 24, 31];
+        for x in sampled {
+            for y in 0..64 {
+                for z in sampled {
+                    let (b_x, b_z) = (x as i32 - 32, z as i32 + 64);
+                    let expected = block_gen(b_x, y as i32, b_z, cfg.clone());
+                    assert_eq!(
+                        volume.get(x, y, z).block_id,
+                        expected.block_id
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn overlapping_chunks_agree()
+    {
+        let cfg = WorldCfg {
+            sea_level: 19,
+            ..WorldCfg::default()
+        };
+        let a = gen_chunk([0, 0, 0], 32, 64, &cfg);
+        let b = gen_chunk([16, 0, 16], 32, 64, &cfg);
+
+        for x in 16..32 {
+            for y in 0..64 {
+                for z in 16..32 {
+                    assert_eq!(
+                        a.get(x, y, z).block_id,
+                        b.get(x - 16, y, z - 16).block_id
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stacked_chunks_agree()
+    {
+        let cfg = WorldCfg::default();
+        let tall = gen_chunk([0, -32, 0], 32, 96, &cfg);
+        let upper = gen_chunk([0, 32, 0], 32, 32, &cfg);
+        let below = gen_chunk([0, -32, 0], 32, 32, &cfg);
+
+        for x in 0..32 {
+            for y in 0..32 {
+                for z in 0..32 {
+                    assert_eq!(
+                        tall.get(x, y + 64, z).block_id,
+                        upper.get(x, y, z).block_id
+                    );
+                    assert_eq!(
+                        tall.get(x, y, z).block_id,
+                        below.get(x, y, z).block_id
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ground_goes_on_below_zero()
+    {
+        let old = WorldCfg {
+            generator_version: 2,
+            ..WorldCfg::default()
+        };
+        let solid = |cfg: &WorldCfg| {
+            let volume = gen_chunk([0, -32, 0], 32, 32, cfg);
+            let mut solid = 0;
+            for x in 0..32 {
+                for y in 0..32 {
+                    for z in 0..32 {
+                        solid += (volume.get(x, y, z).block_id
+                            != block_ids::AIR)
+                            as usize;
+                    }
+                }
+            }
+            solid
+        };
+
+        assert_eq!(solid(&old), 0);
+        // caves hollow some of it out
+        assert!(solid(&WorldCfg::default()) > 32 * 32 * 32 / 2);
+    }
+
+    #[test]
+    fn column_strata()
+    {
+        let cfg = WorldCfg {
+            sea_level: 19,
+            ..WorldCfg::default()
+        };
+        let column = ColumnSample {
+            height:      30.5,
+            biome:       Biome::Plains,
+            water_level: 19,
+        };
+        let id = |y| chunk::block_in_column(&column, y, &cfg).block_id;
+
+        assert_eq!(id(31), block_ids::AIR);
+        assert_eq!(id(30), block_ids::GRASS);
+        assert_eq!(id(29), block_ids::DIRT);
+        assert_eq!(id(27), block_ids::DIRT);
+        assert_eq!(id(26), block_ids::STONE);
+        assert_eq!(id(0), block_ids::STONE);
+        assert_eq!(id(-200), block_ids::STONE);
+
+        let cfg = WorldCfg {
+            generator_version: 2,
+            ..cfg
+        };
+        let id = |y| chunk::block_in_column(&column, y, &cfg).block_id;
+        assert_eq!(id(0), block_ids::BEDROCK);
+        assert_eq!(id(-1), block_ids::AIR);
+    }
+
+    #[test]
+    fn water_fills_below_sea_level()
+    {
+        let cfg = WorldCfg {
+            sea_level: 19,
+            ..WorldCfg::default()
+        };
+        let column = ColumnSample {
+            height:      10.5,
+            biome:       Biome::Plains,
+            water_level: 19,
+        };
+        let id = |y| chunk::block_in_column(&column, y, &cfg).block_id;
+
+        assert_eq!(id(19), block_ids::AIR);
+        assert_eq!(id(18), block_ids::WATER);
+        assert_eq!(id(11), block_ids::WATER);
+        assert_eq!(id(10), block_ids::SAND);
+        assert_eq!(id(7), block_ids::SAND);
+        assert_eq!(id(6), block_ids::STONE);
+    }
 }
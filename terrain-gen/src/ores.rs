@@ -0,0 +1,194 @@
+use crate::chunk::BlockVolume;
+use crate::config::{MAX_VEIN_SIZE, OreCfg};
+use crate::rng::Rng;
+use crate::{Block, SeedStream, WorldCfg, block_ids};
+
+/// side length in blocks of an ore placement cell, the area
+/// [`OreCfg::frequency`] counts veins in.
+const CELL_SIZE: i32 = 32;
+
+/// steps a vein's random walk can take along each axis.
+const DIRECTIONS: [[i32; 3]; 6] = [
+    [1, 0, 0],
+    [-1, 0, 0],
+    [0, 1, 0],
+    [0, -1, 0],
+    [0, 0, 1],
+    [0, 0, -1],
+];
+
+/// # category
+/// **client side processing**
+///
+/// world positions of every block of the veins of one ore rooted in
+/// placement cell `cell_x`, `cell_z`.
+///
+/// each vein starts at a random position in the cell and grows by a random
+/// walk, one block per step. `ore_index` is the ore's position in
+/// [`WorldCfg::ores`], every ore draws from its own stream so adding an ore
+/// doesn't move the others.
+pub fn veins_in_cell(
+    cell_x: i32,
+    cell_z: i32,
+    ore_index: usize,
+    ore: &OreCfg,
+    cfg: &WorldCfg,
+) -> Vec<[i32; 3]>
+{
+    let seed = cfg.sub_seed(SeedStream::Ores);
+    let mut rng = Rng::for_cell(seed, ore_index as u64, cell_x, cell_z);
+    let extra = rng.chance(ore.frequency.fract());
+    let veins = ore.frequency as u32 + extra as u32;
+
+    let mut blocks = Vec::with_capacity((veins * ore.vein_size) as usize);
+    for _ in 0..veins {
+        let mut pos = [
+            cell_x * CELL_SIZE + rng.range(0, CELL_SIZE),
+            rng.range(ore.min_height, ore.max_height + 1),
+            cell_z * CELL_SIZE + rng.range(0, CELL_SIZE),
+        ];
+        for _ in 0..ore.vein_size {
+            blocks.push(pos);
+            let step = DIRECTIONS[rng.range(0, 6) as usize];
+            pos = [pos[0] + step[0], pos[1] + step[1], pos[2] + step[2]];
+        }
+    }
+
+    blocks
+}
+
+/// cells whose veins can reach the world columns `min..=max` on one axis.
+fn cell_range(min: i32, max: i32) -> std::ops::RangeInclusive<i32>
+{
+    let reach = MAX_VEIN_SIZE as i32;
+    (min - reach).div_euclid(CELL_SIZE)..=(max + reach).div_euclid(CELL_SIZE)
+}
+
+/// # category
+/// **client side processing**
+///
+/// replaces stone in a generated volume with the configured ore veins.
+///
+/// veins rooted in neighboring cells are rebuilt too and only the part
+/// inside the volume written, so a vein crossing a chunk border is the same
+/// on both sides. only stone is replaced, veins never show up in soil,
+/// water or air.
+pub fn place(volume: &mut BlockVolume, cfg: &WorldCfg)
+{
+    let [origin_x, origin_y, origin_z] = volume.origin;
+    let size = volume.size_xz as i32;
+
+    for (ore_index, ore) in cfg.ores.iter().enumerate() {
+        let Some(block_id) = block_ids::by_name(&ore.block) else {
+            continue;
+        };
+        // whole volume above or below every vein
+        let reach = ore.vein_size as i32;
+        if origin_y > ore.max_height + reach
+            || origin_y + (volume.height as i32) < ore.min_height - reach
+        {
+            continue;
+        }
+
+        for cell_x in cell_range(origin_x, origin_x + size - 1) {
+            for cell_z in cell_range(origin_z, origin_z + size - 1) {
+                let veins = veins_in_cell(cell_x, cell_z, ore_index, ore, cfg);
+                for [x, y, z] in veins {
+                    let (lx, ly, lz) =
+                        (x - origin_x, y - origin_y, z - origin_z);
+                    if lx < 0
+                        || lx >= size
+                        || lz < 0
+                        || lz >= size
+                        || ly < 0
+                        || ly >= volume.height as i32
+                    {
+                        continue;
+                    }
+
+                    let (lx, ly, lz) = (lx as usize, ly as usize, lz as usize);
+                    if volume.get(lx, ly, lz).block_id == block_ids::STONE {
+                        volume.set(lx, ly, lz, Block {
+                            block_id,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// the ore of the stone block at world `x`, `y`, `z`, if a vein runs through
+/// it. per-block counterpart of [`place`].
+pub fn ore_at(x: i32, y: i32, z: i32, cfg: &WorldCfg) -> Option<usize>
+{
+    for (ore_index, ore) in cfg.ores.iter().enumerate() {
+        for cell_x in cell_range(x, x) {
+            for cell_z in cell_range(z, z) {
+                let veins = veins_in_cell(cell_x, cell_z, ore_index, ore, cfg);
+                if veins.contains(&[x, y, z]) {
+                    return block_ids::by_name(&ore.block);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn stone_volume(origin: [i32; 3], size_xz: usize, height: usize)
+    -> BlockVolume
+    {
+        let mut volume = BlockVolume::new(origin, size_xz, height);
+        for x in 0..size_xz {
+            for y in 0..height {
+                for z in 0..size_xz {
+                    volume.set(x, y, z, Block {
+                        block_id: block_ids::STONE,
+                    });
+                }
+            }
+        }
+        volume
+    }
+
+    #[test]
+    fn veins_agree_across_chunks_and_stay_in_range()
+    {
+        let cfg = WorldCfg::default();
+        let mut whole = stone_volume([0, 0, 0], 64, 80);
+        let mut part = stone_volume([32, 16, 16], 32, 32);
+        place(&mut whole, &cfg);
+        place(&mut part, &cfg);
+
+        for x in 0..32 {
+            for y in 0..32 {
+                for z in 0..32 {
+                    assert_eq!(
+                        whole.get(x + 32, y + 16, z + 16).block_id,
+                        part.get(x, y, z).block_id
+                    );
+                }
+            }
+        }
+
+        let mut diamonds = 0;
+        for x in 0..64 {
+            for y in 0..80 {
+                for z in 0..64 {
+                    if whole.get(x, y, z).block_id == block_ids::DIAMOND_ORE {
+                        // started at most 12 high, 4 blocks per vein
+                        assert!(y < 12 + 4);
+                        diamonds += 1;
+                    }
+                }
+            }
+        }
+        assert!(diamonds > 0);
+    }
+}
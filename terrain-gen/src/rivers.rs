@@ -0,0 +1,456 @@
+use crate::chunk::{self, ColumnSample};
+use crate::config::WorldCfg;
+use crate::fifo_cache::FifoCache;
+use crate::rng::Rng;
+use crate::{SeedStream, erosion, generator};
+use std::f32::consts::FRAC_1_SQRT_2 as DIAGONAL;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, LazyLock, Mutex};
+
+/// side length in blocks of a spring cell, each has at most one spring.
+const SPRING_CELL: i32 = 256;
+
+/// spots tried for the spring of a cell, the highest one is used.
+const SPRING_TRIES: usize = 6;
+
+/// distance in blocks between two points of a river's course.
+const STEP: f32 = 4.0;
+
+/// longest river in steps. rivers that haven't reached the sea by then are
+/// dropped.
+const MAX_STEPS: usize = 256;
+
+/// distance in blocks between the points a spring looks at to pick the
+/// river's heading.
+const SCOUT_DISTANCE: f32 = 64.0;
+
+/// points a spring looks at in each direction.
+const SCOUT_STEPS: usize = 8;
+
+/// how far in blocks past its channel a river cuts back its banks, the banks
+/// slope up by one block per block.
+const BANK_REACH: f32 = 12.0;
+
+/// side length in blocks of the tiles river segments are sorted into, so a
+/// column only checks the segments near it.
+const TILE_SIZE: i32 = 64;
+
+/// traced rivers kept in memory, one per spring cell.
+const MAX_CACHED_RIVERS: usize = 1024;
+
+/// segment lists kept in memory, one per tile.
+const MAX_CACHED_TILES: usize = 1024;
+
+/// the eight directions a river can take from one point to the next.
+const DIRECTIONS: [[f32; 2]; 8] = [
+    [1.0, 0.0],
+    [DIAGONAL, DIAGONAL],
+    [0.0, 1.0],
+    [-DIAGONAL, DIAGONAL],
+    [-1.0, 0.0],
+    [-DIAGONAL, -DIAGONAL],
+    [0.0, -1.0],
+    [DIAGONAL, -DIAGONAL],
+];
+
+/// a point of a river's course.
+#[derive(Debug, Clone, Copy)]
+pub struct RiverPoint
+{
+    pub x:       f32,
+    pub z:       f32,
+    /// height of the water surface, never rises downstream.
+    pub surface: f32,
+    /// half width of the channel.
+    pub width:   f32,
+}
+
+type River = Option<Arc<[RiverPoint]>>;
+
+type Segment = [RiverPoint; 2];
+
+type CacheKey = (u64, i32, i32);
+
+/// traced rivers shared by every generator in the process, keyed by
+/// [`settings_key`] and the spring cell.
+static RIVER_CACHE: LazyLock<Mutex<FifoCache<CacheKey, River>>> =
+    LazyLock::new(|| Mutex::new(FifoCache::new(MAX_CACHED_RIVERS)));
+
+/// segments passing near each tile, keyed by [`settings_key`] and the tile.
+static TILE_CACHE: LazyLock<Mutex<FifoCache<CacheKey, Arc<[Segment]>>>> =
+    LazyLock::new(|| Mutex::new(FifoCache::new(MAX_CACHED_TILES)));
+
+/// identifies every setting the land and the rivers depend on, so worlds
+/// with different settings never share cached rivers.
+fn settings_key(cfg: &WorldCfg) -> u64
+{
+    let mut hasher = DefaultHasher::new();
+    erosion::hash_height_settings(cfg, &mut hasher);
+
+    let rivers = &cfg.rivers;
+    cfg.sea_level.hash(&mut hasher);
+    for value in [
+        rivers.frequency,
+        rivers.source_height,
+        rivers.width,
+        rivers.depth,
+    ] {
+        value.to_bits().hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
+/// heights of the land rivers are traced across under every world position
+/// of `points`, sampled in one batch. skips erosion, which would have to
+/// simulate every tile a river passes, the channels are cut into the eroded
+/// land all the same.
+fn land_heights(points: &[[f32; 2]], cfg: &WorldCfg) -> Vec<f32>
+{
+    let xs: Vec<i32> = points.iter().map(|p| p[0].floor() as i32).collect();
+    let zs: Vec<i32> = points.iter().map(|p| p[1].floor() as i32).collect();
+    chunk::base_columns_at(&xs, &zs, cfg)
+        .into_iter()
+        .map(|column| column.height)
+        .collect()
+}
+
+/// number of eighths of a turn between directions `a` and `b`.
+fn turn(a: usize, b: usize) -> usize
+{
+    ((a + 8 - b) % 8).min((b + 8 - a) % 8)
+}
+
+/// lowest land on the way out from world `x`, `z` in each direction, up to
+/// `SCOUT_STEPS * SCOUT_DISTANCE` blocks away.
+fn lowest_ahead(x: f32, z: f32, cfg: &WorldCfg) -> [f32; 8]
+{
+    let points: Vec<[f32; 2]> = DIRECTIONS
+        .iter()
+        .flat_map(|[dx, dz]| {
+            (1..=SCOUT_STEPS)
+                .map(|i| i as f32 * SCOUT_DISTANCE)
+                .map(move |r| [x + dx * r, z + dz * r])
+        })
+        .collect();
+    let heights = land_heights(&points, cfg);
+
+    let mut lowest = [f32::INFINITY; 8];
+    for (lowest, heights) in lowest.iter_mut().zip(heights.chunks(SCOUT_STEPS))
+    {
+        *lowest = heights.iter().copied().fold(f32::INFINITY, f32::min);
+    }
+    lowest
+}
+
+/// world position and land height of the spring of cell `cell_x`, `cell_z`,
+/// if it has one. far cheaper than tracing the river from it.
+fn spring(cell_x: i32, cell_z: i32, cfg: &WorldCfg) -> Option<([f32; 2], f32)>
+{
+    let rivers = &cfg.rivers;
+    let seed = cfg.sub_seed(SeedStream::Rivers);
+    let mut rng = Rng::for_cell(seed, 0, cell_x, cell_z);
+    if !rng.chance(rivers.frequency) {
+        return None;
+    }
+
+    let spots: Vec<[f32; 2]> = (0..SPRING_TRIES)
+        .map(|_| {
+            let x = cell_x * SPRING_CELL + rng.range(0, SPRING_CELL);
+            let z = cell_z * SPRING_CELL + rng.range(0, SPRING_CELL);
+            [x as f32 + 0.5, z as f32 + 0.5]
+        })
+        .collect();
+    let heights = land_heights(&spots, cfg);
+    let (spot, height) = spots
+        .into_iter()
+        .zip(heights)
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    (height >= cfg.sea_level as f32 + rivers.source_height)
+        .then_some((spot, height))
+}
+
+/// # category
+/// **client side processing**
+///
+/// the course of the river springing in cell `cell_x`, `cell_z`, if it has
+/// one.
+///
+/// the spring is the highest of a few random spots in the cell, and must be
+/// at least `source_height` above the sea. the river heads for the lowest
+/// land in sight and steps to the lowest of the points ahead of it until it
+/// reaches the sea. it never strays far from its heading, so instead of
+/// circling in a dip it carries on and cuts a gorge through whatever is in
+/// the way. only depends on the settings and the cell, so every chunk sees
+/// the same river.
+pub fn trace_river(
+    cell_x: i32,
+    cell_z: i32,
+    cfg: &WorldCfg,
+) -> Option<Vec<RiverPoint>>
+{
+    trace_from(spring(cell_x, cell_z, cfg)?, cfg)
+}
+
+/// the course of the river from `spring`, see [`trace_river`].
+fn trace_from(spring: ([f32; 2], f32), cfg: &WorldCfg)
+-> Option<Vec<RiverPoint>>
+{
+    let rivers = &cfg.rivers;
+    let sea_level = cfg.sea_level as f32;
+    let ([mut x, mut z], spring_height) = spring;
+
+    // head for the lowest land in sight, so a river can't circle in a dip
+    let (heading, _) = lowest_ahead(x, z, cfg)
+        .into_iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    let mut course = Vec::new();
+    let mut surface = spring_height;
+    let mut direction = heading;
+    let mut ground = spring_height;
+    for _ in 0..MAX_STEPS {
+        // the water sits a block below the ground and never flows uphill
+        surface = surface.min(ground - 1.0).max(sea_level);
+        course.push(RiverPoint {
+            x,
+            z,
+            surface,
+            width: rivers.width,
+        });
+
+        if ground <= sea_level {
+            // widen towards the mouth
+            let last = (course.len() - 1).max(1) as f32;
+            for (i, point) in course.iter_mut().enumerate() {
+                point.width *= 1.0 + i as f32 / last;
+            }
+            return Some(course);
+        }
+
+        // turn by at most 45 degrees per step and 90 from the heading
+        let turns: Vec<usize> = (direction + 7..=direction + 9)
+            .map(|d| d % 8)
+            .filter(|&d| turn(d, heading) <= 2)
+            .collect();
+        let ahead: Vec<[f32; 2]> = turns
+            .iter()
+            .map(|&d| {
+                let [dx, dz] = DIRECTIONS[d];
+                [x + dx * STEP, z + dz * STEP]
+            })
+            .collect();
+        let next = turns
+            .iter()
+            .zip(&ahead)
+            .zip(land_heights(&ahead, cfg))
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+        let ((&d, &[nx, nz]), height) = next;
+        (direction, x, z, ground) = (d, nx, nz, height);
+    }
+
+    None
+}
+
+/// the river springing in a cell, from the cache if possible. `None`
+/// without tracing it when its spring is further than `reach` blocks from
+/// the area `min` to `max`, so it can't get there.
+fn river(
+    key: u64,
+    cell_x: i32,
+    cell_z: i32,
+    [min, max]: [[f32; 2]; 2],
+    reach: f32,
+    cfg: &WorldCfg,
+) -> River
+{
+    let key = (key, cell_x, cell_z);
+    if let Some(river) = RIVER_CACHE.lock().unwrap().get(&key) {
+        return river;
+    }
+
+    // not cached, whether it is in reach depends on the area
+    let spring = spring(cell_x, cell_z, cfg);
+    if let Some(([x, z], _)) = spring {
+        let dx = (min[0] - x).max(x - max[0]).max(0.0);
+        let dz = (min[1] - z).max(z - max[1]).max(0.0);
+        if dx * dx + dz * dz > reach * reach {
+            return None;
+        }
+    }
+
+    // trace without holding the lock, like erosion tiles
+    let river: River = spring
+        .and_then(|spring| trace_from(spring, cfg))
+        .map(Arc::from);
+    RIVER_CACHE.lock().unwrap().insert(key, river.clone());
+    river
+}
+
+/// every river segment that can change a column of the tile, from the
+/// cache if possible.
+fn tile_segments(
+    key: u64,
+    tile_x: i32,
+    tile_z: i32,
+    cfg: &WorldCfg,
+) -> Arc<[Segment]>
+{
+    let cache_key = (key, tile_x, tile_z);
+    if let Some(tile) = TILE_CACHE.lock().unwrap().get(&cache_key) {
+        return tile;
+    }
+
+    let min_x = (tile_x * TILE_SIZE) as f32;
+    let min_z = (tile_z * TILE_SIZE) as f32;
+    let (max_x, max_z) = (min_x + TILE_SIZE as f32, min_z + TILE_SIZE as f32);
+    // widest a river's influence gets, at its mouth
+    let margin = cfg.rivers.width * 2.0 + BANK_REACH;
+
+    // springs further away than the longest river can't reach the tile.
+    // only rivers springing within that distance are traced, the cells are
+    // a square around the tile and only the springs in a circle are close
+    let reach = MAX_STEPS as f32 * STEP + margin;
+    let cells = |min: f32, max: f32| {
+        ((min - reach) as i32).div_euclid(SPRING_CELL)
+            ..=((max + reach) as i32).div_euclid(SPRING_CELL)
+    };
+    let area = [[min_x, min_z], [max_x, max_z]];
+
+    let mut segments = Vec::new();
+    for cell_x in cells(min_x, max_x) {
+        for cell_z in cells(min_z, max_z) {
+            let river = river(key, cell_x, cell_z, area, reach, cfg);
+            let Some(course) = river else {
+                continue;
+            };
+            for pair in course.windows(2) {
+                let [a, b] = [pair[0], pair[1]];
+                if a.x.max(b.x) + margin >= min_x
+                    && a.x.min(b.x) - margin <= max_x
+                    && a.z.max(b.z) + margin >= min_z
+                    && a.z.min(b.z) - margin <= max_z
+                {
+                    segments.push([a, b]);
+                }
+            }
+        }
+    }
+
+    let tile: Arc<[Segment]> = segments.into();
+    TILE_CACHE.lock().unwrap().insert(cache_key, Arc::clone(&tile));
+    tile
+}
+
+/// # category
+/// **client side processing**
+///
+/// cuts the channels and banks of every river passing the column at world
+/// `x`, `z` into it, and raises its water level to the river's surface.
+///
+/// channels are bowl shaped, `depth` deep in the middle. the water surface
+/// is kept a block below the column's own land so rivers crossing a slope
+/// don't stand above the ground next to them.
+pub fn carve_column(x: i32, z: i32, column: &mut ColumnSample, cfg: &WorldCfg)
+{
+    RiverCarver::new(cfg).carve_column(x, z, column);
+}
+
+/// # category
+/// **client side processing**
+///
+/// [`carve_column`] for many columns of one world, which only hashes the
+/// world's settings once instead of once per column.
+pub struct RiverCarver<'a>
+{
+    cfg: &'a WorldCfg,
+    key: u64,
+}
+
+impl<'a> RiverCarver<'a>
+{
+    pub fn new(cfg: &'a WorldCfg) -> Self
+    {
+        Self {
+            cfg,
+            key: settings_key(cfg),
+        }
+    }
+
+    /// see [`carve_column`].
+    pub fn carve_column(&self, x: i32, z: i32, column: &mut ColumnSample)
+    {
+        carve(x, z, column, self.key, self.cfg);
+    }
+}
+
+/// [`carve_column`] with the world's [`settings_key`].
+fn carve(x: i32, z: i32, column: &mut ColumnSample, key: u64, cfg: &WorldCfg)
+{
+    let tile_x = x.div_euclid(TILE_SIZE);
+    let tile_z = z.div_euclid(TILE_SIZE);
+    let segments = tile_segments(key, tile_x, tile_z, cfg);
+    let (px, pz) = (x as f32 + 0.5, z as f32 + 0.5);
+    let land = column.height;
+
+    for [a, b] in segments.iter() {
+        // closest point of the segment
+        let (dx, dz) = (b.x - a.x, b.z - a.z);
+        let t = (((px - a.x) * dx + (pz - a.z) * dz) / (dx * dx + dz * dz))
+            .clamp(0.0, 1.0);
+        let (ox, oz) = (px - a.x - dx * t, pz - a.z - dz * t);
+        let distance = generator::length(cfg.generator_version, ox, oz);
+        let width = a.width + (b.width - a.width) * t;
+        let surface = (a.surface + (b.surface - a.surface) * t)
+            .min(land - 1.0)
+            .max(cfg.sea_level as f32);
+
+        if distance < width {
+            let edge = distance / width;
+            let bed = surface - cfg.rivers.depth * (1.0 - edge * edge);
+            column.height = column.height.min(bed);
+            column.water_level = column.water_level.max(surface as i32);
+        } else if distance < width + BANK_REACH {
+            let bank = surface + 1.0 + (distance - width);
+            column.height = column.height.min(bank);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::RiverCfg;
+
+    #[test]
+    fn rivers_flow_downhill_into_water()
+    {
+        let cfg = WorldCfg {
+            rivers: RiverCfg {
+                frequency: 1.0,
+                ..RiverCfg::default()
+            },
+            ..WorldCfg::default()
+        };
+
+        let course = (-8..8)
+            .flat_map(|x| (-8..8).map(move |z| (x, z)))
+            .find_map(|(x, z)| trace_river(x, z, &cfg))
+            .expect("some cell has a river");
+
+        for pair in course.windows(2) {
+            assert!(pair[1].surface <= pair[0].surface);
+        }
+        let mouth = course.last().unwrap();
+        let mouth_height = land_heights(&[[mouth.x, mouth.z]], &cfg)[0];
+        assert!(mouth_height <= cfg.sea_level as f32);
+
+        // the middle of the course is a channel full of water
+        let middle = course[course.len() / 2];
+        let (x, z) = (middle.x.floor() as i32, middle.z.floor() as i32);
+        let column = chunk::column_sample(x, z, &cfg);
+        assert!((column.height.ceil() as i32) < column.water_level);
+    }
+}
@@ -0,0 +1,71 @@
+/// splitmix64 increment, 2^64 divided by the golden ratio.
+pub(crate) const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// splitmix64 output function. scrambles every input bit into every output
+/// bit, so nearby inputs give unrelated outputs.
+pub(crate) fn mix64(mut z: u64) -> u64
+{
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// # category
+/// **client side processing**
+///
+/// small deterministic rng (splitmix64) for placement decisions.
+///
+/// noise answers "how much" at a point, this answers "where and which" for
+/// discrete things like trees. seeding it from a grid position gives every
+/// cell of the world its own independent stream.
+#[derive(Debug, Clone)]
+pub struct Rng
+{
+    state: u64,
+}
+
+impl Rng
+{
+    pub fn new(seed: u64) -> Self
+    {
+        Self {
+            state: seed,
+        }
+    }
+
+    /// rng for the grid cell `x`, `z`. `salt` keeps different users of the
+    /// same grid (trees, ores, ...) from drawing the same numbers.
+    pub fn for_cell(seed: u64, salt: u64, x: i32, z: i32) -> Self
+    {
+        let salted = seed ^ salt.wrapping_mul(GOLDEN_GAMMA);
+        let mut rng = Self::new(salted);
+        let cell = ((x as u32 as u64) << 32) | (z as u32 as u64);
+        rng.state ^= rng.next_u64() ^ cell;
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64
+    {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        mix64(self.state)
+    }
+
+    /// uniform float in `0.0..1.0`.
+    pub fn next_f32(&mut self) -> f32
+    {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// uniform integer in `lo..hi`.
+    pub fn range(&mut self, lo: i32, hi: i32) -> i32
+    {
+        debug_assert!(lo < hi);
+        lo + (self.next_u64() % (hi - lo) as u64) as i32
+    }
+
+    /// true with probability `p`.
+    pub fn chance(&mut self, p: f32) -> bool
+    {
+        self.next_f32() < p
+    }
+}
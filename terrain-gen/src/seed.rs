@@ -0,0 +1,167 @@
+use crate::rng::{GOLDEN_GAMMA, mix64};
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// fnv-1a 64 bit offset basis.
+const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+
+/// fnv-1a 64 bit prime.
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+/// # category
+/// **client side processing**
+///
+/// the parts of world generation that each draw from their own seed.
+///
+/// every stream's seed is derived from the world seed by hashing, so caves
+/// don't follow the hills and biome borders don't line up with ravines, while
+/// the whole world still comes from one number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedStream
+{
+    /// terrain height layers.
+    Height,
+    /// temperature and humidity.
+    Climate,
+    Caves,
+    Ravines,
+    Ores,
+    /// trees, cacti and boulders.
+    Decoration,
+    /// where erosion droplets start.
+    Erosion,
+    /// 3d noise of density worlds.
+    Density,
+    /// where rivers spring.
+    Rivers,
+    /// dungeon layouts.
+    Dungeons,
+}
+
+impl SeedStream
+{
+    /// fixed per stream. changing one changes that part of every world.
+    fn salt(self) -> u64
+    {
+        match self {
+            SeedStream::Height => 1,
+            SeedStream::Climate => 2,
+            SeedStream::Caves => 3,
+            SeedStream::Ravines => 4,
+            SeedStream::Ores => 5,
+            SeedStream::Decoration => 6,
+            SeedStream::Erosion => 7,
+            SeedStream::Density => 8,
+            SeedStream::Rivers => 9,
+            SeedStream::Dungeons => 10,
+        }
+    }
+}
+
+/// # category
+/// **client side processing**
+///
+/// seed of `stream` in the world seeded with `world_seed`.
+pub fn sub_seed(world_seed: u64, stream: SeedStream) -> u64
+{
+    mix64(world_seed ^ mix64(stream.salt().wrapping_mul(GOLDEN_GAMMA)))
+}
+
+/// # category
+/// **client side processing**
+///
+/// 32 bit seed for the `index`th noise field of a stream, noiselib only takes
+/// 32 bit seeds.
+pub fn noise_seed(stream_seed: u64, index: u32) -> u32
+{
+    let offset = (index as u64 + 1).wrapping_mul(GOLDEN_GAMMA);
+    let mixed = mix64(stream_seed.wrapping_add(offset));
+    (mixed ^ (mixed >> 32)) as u32
+}
+
+/// # category
+/// **client side processing**
+///
+/// world seed from text typed by a player.
+///
+/// decimal numbers, negative ones included, are used as they are. anything
+/// else is hashed, so "my world" gives the same world on every machine.
+pub fn parse(text: &str) -> u64
+{
+    let text = text.trim();
+    if let Ok(seed) = text.parse::<u64>() {
+        return seed;
+    }
+    if let Ok(seed) = text.parse::<i64>() {
+        return seed as u64;
+    }
+
+    let hash = text.bytes().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    });
+    mix64(hash)
+}
+
+/// reads a seed given either as a toml integer or as text, see [`parse`].
+pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawSeed
+    {
+        Number(i64),
+        Text(String),
+    }
+
+    Ok(match RawSeed::deserialize(deserializer)? {
+        RawSeed::Number(seed) => seed as u64,
+        RawSeed::Text(text) => parse(&text),
+    })
+}
+
+/// writes a seed as decimal text. toml integers are signed 64 bit, so large
+/// seeds would not fit in one.
+pub(crate) fn serialize<S>(
+    seed: &u64,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&seed.to_string())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn text_seeds_are_stable_and_numbers_pass_through()
+    {
+        assert_eq!(parse("42"), 42);
+        assert_eq!(parse(" 18446744073709551615 "), u64::MAX);
+        assert_eq!(parse("-1"), u64::MAX);
+        assert_eq!(parse("my world"), parse("my world"));
+        assert_ne!(parse("my world"), parse("my world!"));
+
+        let streams = [
+            SeedStream::Height,
+            SeedStream::Climate,
+            SeedStream::Caves,
+            SeedStream::Ravines,
+            SeedStream::Ores,
+            SeedStream::Decoration,
+            SeedStream::Erosion,
+            SeedStream::Density,
+            SeedStream::Rivers,
+            SeedStream::Dungeons,
+        ];
+        for (i, a) in streams.iter().enumerate() {
+            for b in &streams[i + 1..] {
+                assert_ne!(sub_seed(10, *a), sub_seed(10, *b));
+            }
+        }
+    }
+}
@@ -1,28 +1,393 @@
+use crate::batch_noise;
+use crate::config::{NoiseKind, NoiseLayer};
+use crate::generator::OWN_3D_NOISE;
 use noiselib::{
-    fractal::fractal_noise_add_2d,
-    perlin::perlin_noise_2d,
+    fractal::fractal_noise_add_3d, perlin::perlin_noise_3d,
     prelude::UniformRandomGen,
 };
 
-pub fn terrain_noise_2d(
-    rng: &mut UniformRandomGen,
+/// offsets between the two warp fields, so `x` and `y` are pushed
+/// independently.
+const WARP_OFFSET: [f32; 2] = [5.2, 1.3];
+
+/// noiselib's lattice repeats every 256 units.
+const LATTICE_SIZE: f32 = 256.0;
+
+/// offset between the lattices of consecutive 3d noise octaves.
+const OCTAVE_SHIFT: f32 = 0.37;
+
+/// positions the batch functions work through at a time, so their buffers
+/// fit on the stack.
+const CHUNK: usize = 64;
+
+/// noiselib only keeps the low byte of a seed, which leaves 256 fields. the
+/// other 24 bits are split over the axes and move the sampled point around
+/// the lattice, so seeds that share a low byte still get their own field.
+fn seed_offset<const N: usize>(seed: u32) -> [f32; N]
+{
+    let bits = 24 / N as u32;
+    let step = LATTICE_SIZE / (1 << bits) as f32;
+    std::array::from_fn(|axis| {
+        let field = (seed >> (8 + bits * axis as u32)) & ((1 << bits) - 1);
+        field as f32 * step
+    })
+}
+
+/// runs `batch` over at most [`CHUNK`] positions at a time.
+fn in_chunks(
+    xs: &[f32],
+    ys: &[f32],
+    out: &mut [f32],
+    mut batch: impl FnMut(&[f32], &[f32], &mut [f32]),
+)
+{
+    let chunks = xs.chunks(CHUNK).zip(ys.chunks(CHUNK));
+    for ((xs, ys), out) in chunks.zip(out.chunks_mut(CHUNK)) {
+        batch(xs, ys, out);
+    }
+}
+
+/// a buffer holding `values` scaled by `by` and moved by `offset`.
+fn scaled(values: &[f32], by: f32, offset: f32) -> [f32; CHUNK]
+{
+    let mut scaled = [0.0; CHUNK];
+    for (scaled, value) in scaled.iter_mut().zip(values) {
+        *scaled = value * by + offset;
+    }
+    scaled
+}
+
+/// evaluates a batched noise function at a single position.
+fn single(x: f32, y: f32, batch: impl FnOnce(&[f32], &[f32], &mut [f32]))
+-> f32
+{
+    let mut out = [0.0];
+    batch(&[x], &[y], &mut out);
+    out[0]
+}
+
+/// amplitude weighted sum of `octaves` octaves of perlin noise, each
+/// `lacunarity` times the frequency of the one before, the same values as
+/// noiselib's `fractal_noise_add_2d` gives. like noiselib, every octave uses
+/// the same seed and the sum is divided by one more than the total
+/// amplitude.
+fn fractal_batch(
+    xs: &[f32],
+    ys: &[f32],
+    [octaves, seed]: [u32; 2],
+    [frequency, falloff, lacunarity]: [f32; 3],
+    out: &mut [f32],
+)
+{
+    in_chunks(xs, ys, out, |xs, ys, out| {
+        let len = xs.len();
+        let [ox, oy] = seed_offset(seed);
+        let mut xs = scaled(xs, frequency, ox);
+        let mut ys = scaled(ys, frequency, oy);
+        let mut noise = [0.0; CHUNK];
+        let mut amplitude = 1.0;
+        let mut norm = 1.0;
+
+        out.fill(0.0);
+        for _ in 0..octaves {
+            let at = (&xs[..len], &ys[..len]);
+            batch_noise::perlin_2d(at.0, at.1, seed, &mut noise);
+            for (out, noise) in out.iter_mut().zip(&noise) {
+                *out += noise * amplitude;
+            }
+            norm += amplitude;
+            amplitude *= falloff;
+            xs.iter_mut().for_each(|x| *x *= lacunarity);
+            ys.iter_mut().for_each(|y| *y *= lacunarity);
+        }
+        out.iter_mut().for_each(|out| *out /= norm);
+    });
+}
+
+/// fractal perlin noise shaped by a configured layer at every position
+/// `xs[i]`, `ys[i]`, ignoring its kind, warp and spline.
+pub fn terrain_noise_batch(
+    xs: &[f32],
+    ys: &[f32],
+    layer: &NoiseLayer,
+    seed: u32,
+    out: &mut [f32],
+)
+{
+    let shape = [layer.frequency, layer.falloff, layer.lacunarity];
+    fractal_batch(xs, ys, [layer.octaves, seed], shape, out);
+}
+
+/// single position version of [`terrain_noise_batch`].
+pub fn terrain_noise_2d(x: f32, y: f32, layer: &NoiseLayer, seed: u32) -> f32
+{
+    single(x, y, |xs, ys, out| terrain_noise_batch(xs, ys, layer, seed, out))
+}
+
+/// ridged multifractal noise in `-1.0..=1.0` at every position `xs[i]`,
+/// `ys[i]`.
+///
+/// each octave is folded along its zero line (`1 - |n|`) and sharpened, and is
+/// weighted by the octave before it, so detail gathers on the ridges while
+/// valleys stay smooth.
+pub fn ridged_noise_batch(
+    xs: &[f32],
+    ys: &[f32],
+    layer: &NoiseLayer,
+    seed: u32,
+    out: &mut [f32],
+)
+{
+    in_chunks(xs, ys, out, |xs, ys, out| {
+        let len = xs.len();
+        let [ox, oy] = seed_offset(seed);
+        let mut xs = scaled(xs, layer.frequency, ox);
+        let mut ys = scaled(ys, layer.frequency, oy);
+        let mut noise = [0.0; CHUNK];
+        let mut weights = [1.0_f32; CHUNK];
+        let mut amplitude = 1.0;
+        let mut norm = 0.0;
+
+        out.fill(0.0);
+        for _ in 0..layer.octaves {
+            let at = (&xs[..len], &ys[..len]);
+            batch_noise::perlin_2d(at.0, at.1, seed, &mut noise);
+            let samples = out.iter_mut().zip(&noise).zip(&mut weights);
+            for ((out, noise), weight) in samples {
+                let ridge = 1.0 - noise.abs();
+                let signal = ridge * ridge * *weight;
+                *weight = (signal * 2.0).clamp(0.0, 1.0);
+                *out += signal * amplitude;
+            }
+            norm += amplitude;
+            amplitude *= layer.falloff;
+            xs.iter_mut().for_each(|x| *x *= layer.lacunarity);
+            ys.iter_mut().for_each(|y| *y *= layer.lacunarity);
+        }
+        out.iter_mut().for_each(|out| *out = *out / norm * 2.0 - 1.0);
+    });
+}
+
+/// piecewise linear interpolation through `[input, output]` points sorted by
+/// input. values outside the points are clamped to the end points.
+pub fn eval_spline(points: &[[f32; 2]], t: f32) -> f32
+{
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return t;
+    };
+    if t <= first[0] {
+        return first[1];
+    }
+    if t >= last[0] {
+        return last[1];
+    }
+
+    for pair in points.windows(2) {
+        let ([x0, y0], [x1, y1]) = (pair[0], pair[1]);
+        if t <= x1 {
+            return y0 + (y1 - y0) * (t - x0) / (x1 - x0);
+        }
+    }
+    last[1]
+}
+
+/// samples a configured noise layer at every position `xs[i]`, `ys[i]`:
+/// domain warp, then the layer's noise kind, then its spline.
+pub fn sample_layer_batch(
+    xs: &[f32],
+    ys: &[f32],
+    layer: &NoiseLayer,
+    seed: u32,
+    out: &mut [f32],
+)
+{
+    in_chunks(xs, ys, out, |xs, ys, out| {
+        let len = xs.len();
+        let mut xs = scaled(xs, 1.0, 0.0);
+        let mut ys = scaled(ys, 1.0, 0.0);
+        if layer.warp > 0.0 {
+            // the warp fields share the layer's base frequency, so warping
+            // stretches features of the same size
+            let mut wx = scaled(&xs[..len], layer.frequency, 0.0);
+            let mut wy = scaled(&ys[..len], layer.frequency, 0.0);
+            let warp_seed = seed.wrapping_add(1);
+            let (mut dx, mut dy) = ([0.0; CHUNK], [0.0; CHUNK]);
+            climate_noise_batch(&wx[..len], &wy[..len], warp_seed, &mut dx);
+            wx.iter_mut().for_each(|x| *x += WARP_OFFSET[0]);
+            wy.iter_mut().for_each(|y| *y += WARP_OFFSET[1]);
+            climate_noise_batch(&wx[..len], &wy[..len], warp_seed, &mut dy);
+            for i in 0..len {
+                xs[i] += dx[i] * layer.warp;
+                ys[i] += dy[i] * layer.warp;
+            }
+        }
+
+        let (xs, ys) = (&xs[..len], &ys[..len]);
+        match layer.kind {
+            NoiseKind::Fbm => terrain_noise_batch(xs, ys, layer, seed, out),
+            NoiseKind::Ridged => ridged_noise_batch(xs, ys, layer, seed, out),
+        }
+        out.iter_mut().for_each(|out| *out = eval_spline(&layer.spline, *out));
+    });
+}
+
+/// low detail noise for slowly changing fields like temperature and humidity,
+/// at every position `xs[i]`, `ys[i]`.
+pub fn climate_noise_batch(xs: &[f32], ys: &[f32], seed: u32, out: &mut [f32])
+{
+    // climate should drift smoothly, a few octaves are enough
+    let octaves = 3;
+    let freq_falloff = 0.5;
+    let lacunarity = 2.0;
+
+    let shape = [1.0, freq_falloff, lacunarity];
+    fractal_batch(xs, ys, [octaves, seed], shape, out);
+}
+
+/// single octave 3d noise, used for carving caves out of the solid volume,
+/// as generator version `version` computes it.
+pub fn cave_noise_3d(x: f32, y: f32, z: f32, seed: u32, version: u32) -> f32
+{
+    // tunnels should be smooth tubes, extra octaves only roughen the walls
+    if version >= OWN_3D_NOISE {
+        return batch_noise::gradient_3d_at(x, y, z, seed);
+    }
+
+    // perlin noise never draws from the generator
+    let mut rng = UniformRandomGen::new(seed);
+    let [ox, oy, oz] = seed_offset(seed);
+    perlin_noise_3d(&mut rng, x + ox, y + oy, z + oz, seed)
+}
+
+/// fractal 3d noise deciding which blocks of a density world are solid, as
+/// generator version `version` computes it.
+pub fn density_noise_3d(
     x: f32,
     y: f32,
+    z: f32,
+    octaves: u32,
     seed: u32,
+    version: u32,
 ) -> f32
 {
-    // more octaves means more detail (rocky / jagged)
-    let octaves = 6;
-
-    // how much each successive layer contributes 0.5 means each one is half as
-    // much as the previous
     let freq_falloff = 0.5;
-
-    // lacunarity dictates how quickly the frequency increases per octave.
-    // 2.0 means the detail level doubles each time.
     let lacunarity = 2.0;
 
-    fractal_noise_add_2d(
-        rng, x, y, perlin_noise_2d, octaves, freq_falloff, lacunarity, seed,
-    )
+    if version < OWN_3D_NOISE {
+        let mut rng = UniformRandomGen::new(seed);
+        let [ox, oy, oz] = seed_offset(seed);
+        return fractal_noise_add_3d(
+            &mut rng,
+            x + ox,
+            y + oy,
+            z + oz,
+            perlin_noise_3d,
+            octaves as i32,
+            freq_falloff,
+            lacunarity,
+            seed,
+        );
+    }
+
+    let mut frequency = 1.0;
+    let mut amplitude = 1.0;
+    let mut total = 0.0;
+    let mut norm = 0.0;
+    for octave in 0..octaves {
+        // shifted so the octaves' lattices don't all meet at the origin,
+        // where gradient noise is always 0
+        let shift = octave as f32 * OCTAVE_SHIFT;
+        let (px, py, pz) = (x * frequency, y * frequency, z * frequency);
+        let (px, py, pz) = (px + shift, py + shift, pz + shift);
+        let noise =
+            batch_noise::gradient_3d_at(px, py, pz, seed.wrapping_add(octave));
+        total += noise * amplitude;
+        norm += amplitude;
+        amplitude *= freq_falloff;
+        frequency *= lacunarity;
+    }
+
+    total / norm
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use noiselib::fractal::fractal_noise_add_2d;
+    use noiselib::perlin::perlin_noise_2d;
+    use noiselib::prelude::UniformRandomGen;
+
+    #[test]
+    fn fractal_matches_noiselib_bits()
+    {
+        let layer = NoiseLayer::DEFAULT;
+        let xs: Vec<f32> = (0..100).map(|i| i as f32 * 0.37 - 20.0).collect();
+        let ys: Vec<f32> = (0..100).map(|i| i as f32 * -0.11 + 3.0).collect();
+        let mut batched = vec![0.0; xs.len()];
+        let seed = 0xABC_DE2A;
+        terrain_noise_batch(&xs, &ys, &layer, seed, &mut batched);
+
+        let mut rng = UniformRandomGen::new(seed);
+        let [ox, oy] = seed_offset(seed);
+        for ((x, y), batched) in xs.iter().zip(&ys).zip(&batched) {
+            let noise = fractal_noise_add_2d(
+                &mut rng,
+                x * layer.frequency + ox,
+                y * layer.frequency + oy,
+                perlin_noise_2d,
+                layer.octaves as i32,
+                layer.falloff,
+                layer.lacunarity,
+                seed,
+            );
+            assert_eq!(noise.to_bits(), batched.to_bits());
+        }
+    }
+
+    #[test]
+    fn spline_interpolates_and_clamps()
+    {
+        let points = [[-1.0, -0.5], [0.0, 0.0], [0.5, 1.0]];
+
+        assert_eq!(eval_spline(&points, -2.0), -0.5);
+        assert_eq!(eval_spline(&points, -0.5), -0.25);
+        assert_eq!(eval_spline(&points, 0.25), 0.5);
+        assert_eq!(eval_spline(&points, 0.9), 1.0);
+        assert_eq!(eval_spline(&[], 0.3), 0.3);
+    }
+
+    #[test]
+    fn seeds_sharing_the_low_byte_give_different_heightmaps()
+    {
+        let mut rng = UniformRandomGen::new(0);
+        let (a, b) = (0x1234_5678, 0xA9CB_0E78);
+        // noiselib alone can't tell these seeds apart
+        assert_eq!(
+            perlin_noise_2d(&mut rng, 0.3, 0.7, a),
+            perlin_noise_2d(&mut rng, 0.3, 0.7, b),
+        );
+
+        // the warped ridged layer also covers the climate noise
+        let ridged = NoiseLayer {
+            kind: NoiseKind::Ridged,
+            warp: 0.5,
+            ..NoiseLayer::DEFAULT
+        };
+        let grid = |axis: fn(i32) -> i32| -> Vec<f32> {
+            (0..16 * 16).map(|i| axis(i) as f32 / 16.0).collect()
+        };
+        let (xs, zs) = (grid(|i| i % 16), grid(|i| i / 16));
+        for layer in [NoiseLayer::DEFAULT, ridged] {
+            let heightmap = |seed| {
+                let mut heights = vec![0.0; xs.len()];
+                sample_layer_batch(&xs, &zs, &layer, seed, &mut heights);
+                heights
+            };
+            assert_ne!(heightmap(a), heightmap(b));
+        }
+
+        let cave = |seed| cave_noise_3d(0.3, 0.5, 0.7, seed, 1);
+        assert_ne!(cave(a), cave(b));
+    }
 }
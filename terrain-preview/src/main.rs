@@ -0,0 +1,469 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use terrain_gen::{
+    Biome, GeneratorKind, TerrainGenerator, WorldCfg, block_ids,
+};
+
+/// side length of the tiles the area is generated in, same as the game's
+/// chunks.
+const TILE: usize = 32;
+
+/// blocks above its land a column's top block can be, more than the
+/// tallest tree.
+const FEATURE_HEIGHT: i32 = 16;
+
+const USAGE: &str = "\
+usage: terrain-preview [options]
+
+renders a top-down map of part of a world to a png.
+
+options:
+  --preset <file>   world preset (default resources/worlds/default.toml)
+  --seed <seed>     override the preset's seed, a number or any text
+  --mode <mode>     height, biome or material (default material)
+  --x <n>           west edge in blocks (default -width / 2)
+  --z <n>           north edge in blocks (default -depth / 2)
+  --width <n>       width in blocks and pixels (default 512)
+  --depth <n>       depth in blocks and pixels (default 512)
+  --out <file>      output image (default preview.png)";
+
+/// # category
+/// **tooling**
+///
+/// what the pixels of the map show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode
+{
+    /// grayscale height of the topmost block.
+    Height,
+    /// flat color per biome.
+    Biome,
+    /// color of the topmost block, shaded by height.
+    Material,
+}
+
+/// # category
+/// **tooling**
+///
+/// parsed command line.
+struct Args
+{
+    preset: String,
+    seed:   Option<u64>,
+    mode:   Mode,
+    x:      Option<i32>,
+    z:      Option<i32>,
+    width:  usize,
+    depth:  usize,
+    out:    String,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String>
+{
+    let mut parsed = Args {
+        preset: "resources/worlds/default.toml".to_string(),
+        seed:   None,
+        mode:   Mode::Material,
+        x:      None,
+        z:      None,
+        width:  512,
+        depth:  512,
+        out:    "preview.png".to_string(),
+    };
+
+    fn number<T: std::str::FromStr>(flag: &str, value: String)
+    -> Result<T, String>
+    {
+        value
+            .parse()
+            .map_err(|_| format!("{flag} expects a number, got `{value}`"))
+    }
+
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(String::new());
+        }
+
+        let Some(value) = args.next() else {
+            return Err(format!("{flag} expects a value"));
+        };
+        match flag.as_str() {
+            "--preset" => parsed.preset = value,
+            "--seed" => parsed.seed = Some(terrain_gen::seed::parse(&value)),
+            "--mode" => {
+                parsed.mode = match value.as_str() {
+                    "height" => Mode::Height,
+                    "biome" => Mode::Biome,
+                    "material" => Mode::Material,
+                    _ => return Err(format!("unknown mode `{value}`")),
+                }
+            }
+            "--x" => parsed.x = Some(number(&flag, value)?),
+            "--z" => parsed.z = Some(number(&flag, value)?),
+            "--width" => parsed.width = number(&flag, value)?,
+            "--depth" => parsed.depth = number(&flag, value)?,
+            "--out" => parsed.out = value,
+            _ => return Err(format!("unknown option `{flag}`")),
+        }
+    }
+
+    if parsed.width == 0 || parsed.depth == 0 {
+        return Err("--width and --depth must be greater than 0".to_string());
+    }
+    Ok(parsed)
+}
+
+/// # category
+/// **tooling**
+///
+/// the topmost non-air block of a column, as seen from above.
+#[derive(Debug, Clone, Copy, Default)]
+struct TopBlock
+{
+    y:        i32,
+    block_id: usize,
+}
+
+/// heights the top blocks of the tile at world `origin` can be at.
+///
+/// heightmap worlds take them from the tile's columns. the other generators
+/// take them from their settings, which bound how high their blocks go.
+fn scan_range(cfg: &WorldCfg, origin: [i32; 2]) -> Range<i32>
+{
+    match cfg.generator {
+        GeneratorKind::Hills => {
+            let columns =
+                terrain_gen::column_samples(origin[0], origin[1], TILE, cfg);
+            let land = columns.iter().map(|column| column.height);
+            let low = land.clone().fold(f32::INFINITY, f32::min);
+            let high = columns
+                .iter()
+                .map(|column| column.height.max(column.water_level as f32))
+                .fold(f32::NEG_INFINITY, f32::max);
+            // caves and ravines can open the top of a column, the scan goes
+            // on down until it finds the ground
+            (low as i32 - TILE as i32)..(high.ceil() as i32 + FEATURE_HEIGHT)
+        }
+        GeneratorKind::Density => {
+            let density = &cfg.density;
+            // a finite world sinks the ground to the ocean floor at its border
+            let mut bases = [density.base_height; 2];
+            if cfg.border.is_some() {
+                let floor = Biome::Ocean.profile().base
+                    * cfg.height_scale
+                    * cfg.world_height as f32;
+                bases = [floor.min(bases[0]), floor.max(bases[1])];
+            }
+            // the noise is at most 1, further out the gradient outweighs it
+            let reach = (1.0 + density.bias.abs()) * density.gradient_height;
+            let high = (bases[1] + reach).max(cfg.sea_level as f32);
+            ((bases[0] - reach).floor() as i32)..(high.ceil() as i32 + 1)
+        }
+        GeneratorKind::Superflat => {
+            let layers = cfg.flat_layers.iter().map(|layer| layer.thickness);
+            0..layers.sum::<usize>() as i32
+        }
+        GeneratorKind::Void => 0..0,
+        // the debug grid sits a few blocks above the origin
+        GeneratorKind::Debug => 0..TILE as i32,
+    }
+}
+
+/// the topmost non-air block of every column of a tile, `None` for columns
+/// without one in `range`. generates the tile in slabs from the top of the
+/// range down, until every column found its top.
+fn tile_top_blocks(
+    generator: &dyn TerrainGenerator,
+    origin: [i32; 2],
+    range: Range<i32>,
+) -> Vec<Option<TopBlock>>
+{
+    let mut tops = vec![None; TILE * TILE];
+    let mut slab_top = range.end;
+    while slab_top > range.start && tops.iter().any(Option::is_none) {
+        let height = (slab_top - range.start).min(TILE as i32) as usize;
+        let bottom = slab_top - height as i32;
+        let volume =
+            generator.gen_chunk([origin[0], bottom, origin[1]], TILE, height);
+
+        for x in 0..TILE {
+            for z in 0..TILE {
+                let top = &mut tops[x * TILE + z];
+                if top.is_some() {
+                    continue;
+                }
+                *top = (0..height)
+                    .rev()
+                    .map(|y| (y, volume.get(x, y, z).block_id))
+                    .find(|&(_, id)| id != block_ids::AIR)
+                    .map(|(y, id)| TopBlock {
+                        y:        bottom + y as i32,
+                        block_id: id,
+                    });
+            }
+        }
+        slab_top = bottom;
+    }
+    tops
+}
+
+/// generates every tile overlapping the area and records the top block of
+/// each column, `width * depth` entries in row major order (rows along z).
+fn scan_top_blocks(
+    generator: &dyn TerrainGenerator,
+    cfg: &WorldCfg,
+    origin: [i32; 2],
+    width: usize,
+    depth: usize,
+) -> Vec<TopBlock>
+{
+    let [min_x, min_z] = origin;
+    let tile = TILE as i32;
+    let tile_x0 = min_x.div_euclid(tile);
+    let tile_z0 = min_z.div_euclid(tile);
+    let tiles_x = (min_x + width as i32 - 1).div_euclid(tile) - tile_x0 + 1;
+    let tiles_z = (min_z + depth as i32 - 1).div_euclid(tile) - tile_z0 + 1;
+    let tile_count = (tiles_x * tiles_z) as usize;
+
+    // workers pull tile indices until none are left
+    let next_tile = AtomicUsize::new(0);
+    let workers = thread::available_parallelism().map_or(4, |n| n.get());
+
+    let results: Vec<Vec<(usize, TopBlock)>> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut found = Vec::new();
+                    loop {
+                        let tile = next_tile.fetch_add(1, Ordering::Relaxed);
+                        if tile >= tile_count {
+                            break found;
+                        }
+
+                        let tile_x = tile_x0 + (tile as i32 % tiles_x);
+                        let tile_z = tile_z0 + (tile as i32 / tiles_x);
+                        let origin =
+                            [tile_x * TILE as i32, tile_z * TILE as i32];
+                        let range = scan_range(cfg, origin);
+                        let tops = tile_top_blocks(generator, origin, range);
+
+                        for x in 0..TILE {
+                            for z in 0..TILE {
+                                let px = origin[0] + x as i32 - min_x;
+                                let pz = origin[1] + z as i32 - min_z;
+                                if px < 0
+                                    || pz < 0
+                                    || px >= width as i32
+                                    || pz >= depth as i32
+                                {
+                                    continue;
+                                }
+
+                                let top =
+                                    tops[x * TILE + z].unwrap_or_default();
+                                let index = pz as usize * width + px as usize;
+                                found.push((index, top));
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let mut top_blocks = vec![TopBlock::default(); width * depth];
+    for (index, top) in results.into_iter().flatten() {
+        top_blocks[index] = top;
+    }
+    top_blocks
+}
+
+/// map color of a biome.
+fn biome_color(biome: Option<Biome>) -> [u8; 3]
+{
+    match biome {
+        Some(Biome::Ocean) => [40, 70, 170],
+        Some(Biome::Plains) => [140, 190, 90],
+        Some(Biome::Desert) => [230, 210, 130],
+        Some(Biome::Forest) => [40, 120, 50],
+        Some(Biome::Mountains) => [130, 120, 110],
+        Some(Biome::Tundra) => [220, 230, 240],
+        None => [0, 0, 0],
+    }
+}
+
+fn write_png(
+    path: &str,
+    width: usize,
+    depth: usize,
+    rgb: &[u8],
+) -> Result<(), Box<dyn std::error::Error>>
+{
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width as u32, depth as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgb)?;
+    Ok(())
+}
+
+/// # category
+/// **tooling**
+///
+/// headless top-down preview of generated terrain, for comparing seeds and
+/// preset tweaks without launching the game.
+fn main()
+{
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(msg) => {
+            if !msg.is_empty() {
+                eprintln!("{msg}\n");
+            }
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    let mut cfg = match WorldCfg::load(&args.preset) {
+        Ok(cfg) => cfg,
+        Err(err) => {
+            eprintln!("{}: {err}", args.preset);
+            std::process::exit(1);
+        }
+    };
+    if let Some(seed) = args.seed {
+        cfg.seed = seed;
+    }
+
+    let generator = terrain_gen::generator::from_cfg(&cfg);
+    let origin = [
+        args.x.unwrap_or(-(args.width as i32) / 2),
+        args.z.unwrap_or(-(args.depth as i32) / 2),
+    ];
+    let world_height = cfg.world_height as f32;
+
+    let mut rgb = Vec::with_capacity(args.width * args.depth * 3);
+    if args.mode == Mode::Biome {
+        // biomes come straight from the climate, no need to generate blocks
+        for pz in 0..args.depth {
+            for px in 0..args.width {
+                let biome = generator.biome_at(
+                    origin[0] + px as i32,
+                    origin[1] + pz as i32,
+                );
+                rgb.extend_from_slice(&biome_color(biome));
+            }
+        }
+    } else {
+        let top_blocks = scan_top_blocks(
+            generator.as_ref(),
+            &cfg,
+            origin,
+            args.width,
+            args.depth,
+        );
+
+        for top in top_blocks {
+            let shade = (top.y as f32 / world_height).clamp(0.0, 1.0);
+            let pixel = match args.mode {
+                Mode::Height => [(shade * 255.0) as u8; 3],
+                _ => {
+                    let [r, g, b, _] = block_ids::color(top.block_id);
+                    let light = 0.6 + 0.4 * shade;
+                    [r, g, b].map(|c| (c as f32 * light) as u8)
+                }
+            };
+            rgb.extend_from_slice(&pixel);
+        }
+    }
+
+    if let Err(err) = write_png(&args.out, args.width, args.depth, &rgb) {
+        eprintln!("{}: {err}", args.out);
+        std::process::exit(1);
+    }
+
+    println!(
+        "wrote {} ({}x{} at {}, {}, {} generator, seed {})",
+        args.out,
+        args.width,
+        args.depth,
+        origin[0],
+        origin[1],
+        generator.name(),
+        cfg.seed,
+    );
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use terrain_gen::DensityCfg;
+
+    fn parse(args: &[&str]) -> Result<Args, String>
+    {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn arguments_parse()
+    {
+        let args = parse(&[]).unwrap();
+        assert_eq!(args.preset, "resources/worlds/default.toml");
+        assert_eq!((args.x, args.z), (None, None));
+        assert_eq!((args.width, args.depth), (512, 512));
+
+        let args = parse(&[
+            "--x", "-64", "--z", "128", "--width", "100", "--out", "map.png",
+        ])
+        .unwrap();
+        assert_eq!((args.x, args.z), (Some(-64), Some(128)));
+        assert_eq!((args.width, args.depth), (100, 512));
+        assert_eq!(args.out, "map.png");
+
+        assert!(parse(&["--width", "wide"]).is_err());
+        assert!(parse(&["--depth", "0"]).is_err());
+        assert!(parse(&["--out"]).is_err());
+        assert!(parse(&["--zoom", "2"]).is_err());
+        assert_eq!(parse(&["--help"]).err(), Some(String::new()));
+    }
+
+    #[test]
+    fn modes_parse()
+    {
+        let mode = |name| parse(&["--mode", name]).map(|args| args.mode);
+
+        assert_eq!(parse(&[]).unwrap().mode, Mode::Material);
+        assert_eq!(mode("height"), Ok(Mode::Height));
+        assert_eq!(mode("biome"), Ok(Mode::Biome));
+        assert_eq!(mode("material"), Ok(Mode::Material));
+        assert!(mode("Height").is_err());
+    }
+
+    #[test]
+    fn tops_are_found_below_zero()
+    {
+        let cfg = WorldCfg {
+            generator: GeneratorKind::Density,
+            density:   DensityCfg {
+                base_height: -40.0,
+                ..DensityCfg::DEFAULT
+            },
+            sea_level: 0,
+            ..WorldCfg::default()
+        };
+        let generator = terrain_gen::generator::from_cfg(&cfg);
+        let tops = scan_top_blocks(generator.as_ref(), &cfg, [0, 0], 16, 16);
+
+        assert!(tops.iter().all(|top| top.block_id != block_ids::AIR));
+        assert!(tops.iter().all(|top| top.y < 0));
+    }
+}